@@ -0,0 +1,241 @@
+use crate::{error::BrainsError, gen, nn::gen::MutationSettings};
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, collections::VecDeque};
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct AdaptiveMutationSettings {
+    pub rule: AdaptationRule,
+    /// Number of generations of fitness history that the rule looks at
+    pub window: usize,
+    /// Mutation is multiplied by this factor when it is raised and divided by it when lowered
+    pub adjustment_factor: f64,
+    /// Bounds of the factor that is applied to the configured mutation probability and weights
+    /// affected ratios
+    pub min_scale: f64,
+    pub max_scale: f64,
+}
+
+impl Default for AdaptiveMutationSettings {
+    fn default() -> Self {
+        AdaptiveMutationSettings {
+            rule: AdaptationRule::Stagnation,
+            window: 10,
+            adjustment_factor: 1.5,
+            min_scale: 1.0,
+            max_scale: 4.0,
+        }
+    }
+}
+
+/// A generation counts as a success if its best or mean fitness beats every generation before it
+/// within the window
+#[derive(Deserialize, Serialize, Clone, Copy)]
+pub enum AdaptationRule {
+    /// Raises mutation once the window is full and none of its generations were a success,
+    /// lowers it after every success
+    Stagnation,
+
+    /// Rechenberg's 1/5th success rule, turned around for exploration: Raises mutation while less
+    /// than `target_ratio` (usually 0.2) of the generations in the window were a success, lowers
+    /// it while more were
+    SuccessRule { target_ratio: f64 },
+}
+
+impl AdaptiveMutationSettings {
+    pub fn validate(&self) -> Result<(), BrainsError> {
+        if self.window == 0 {
+            return Err(BrainsError::AdaptiveMutationWindowZero);
+        }
+
+        if self.adjustment_factor <= 1.0 {
+            return Err(BrainsError::AdaptiveMutationInvalidFactor);
+        }
+
+        if self.min_scale <= 0.0 || self.max_scale < self.min_scale {
+            return Err(BrainsError::AdaptiveMutationInvalidScaleBounds);
+        }
+
+        if let AdaptationRule::SuccessRule { target_ratio } = self.rule {
+            if !(0.0..=1.0).contains(&target_ratio) {
+                return Err(BrainsError::AdaptiveMutationInvalidSuccessRatio);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Fitness history and current mutation scale of the adaptive mutation controller
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct MutationController {
+    best: VecDeque<f64>,
+    mean: VecDeque<f64>,
+    successes: VecDeque<bool>,
+    scale: f64,
+}
+
+impl Default for MutationController {
+    fn default() -> Self {
+        MutationController {
+            best: VecDeque::new(),
+            mean: VecDeque::new(),
+            successes: VecDeque::new(),
+            scale: 1.0,
+        }
+    }
+}
+
+impl MutationController {
+    /// Factor that is currently applied to the configured mutation
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// Records the best and mean fitness of a generation and adjusts the mutation scale according
+    /// to the configured rule
+    pub fn update(
+        &mut self,
+        fitness: &[f64],
+        settings: &AdaptiveMutationSettings,
+    ) -> Result<(), BrainsError> {
+        gen::validate_fitness(fitness)?;
+
+        if fitness.is_empty() {
+            return Ok(());
+        }
+
+        let best = fitness.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let mean = fitness.iter().sum::<f64>() / fitness.len() as f64;
+
+        if !self.best.is_empty() {
+            let previous_best = self.best.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            let previous_mean = self.mean.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+            self.successes
+                .push_back(best > previous_best || mean > previous_mean);
+        }
+
+        self.best.push_back(best);
+        self.mean.push_back(mean);
+
+        while self.best.len() > settings.window {
+            self.best.pop_front();
+            self.mean.pop_front();
+        }
+
+        while self.successes.len() > settings.window {
+            self.successes.pop_front();
+        }
+
+        let window_full = self.successes.len() == settings.window;
+
+        let raise = match settings.rule {
+            AdaptationRule::Stagnation => {
+                if window_full && self.successes.iter().all(|s| !s) {
+                    Some(true)
+                } else if self.successes.back() == Some(&true) {
+                    Some(false)
+                } else {
+                    None
+                }
+            }
+            AdaptationRule::SuccessRule { target_ratio } if window_full => {
+                let ratio = self.successes.iter().filter(|&&s| s).count() as f64
+                    / self.successes.len() as f64;
+
+                if ratio < target_ratio {
+                    Some(true)
+                } else if ratio > target_ratio {
+                    Some(false)
+                } else {
+                    None
+                }
+            }
+            AdaptationRule::SuccessRule { .. } => None,
+        };
+
+        self.scale = match raise {
+            Some(true) => self.scale * settings.adjustment_factor,
+            Some(false) => self.scale / settings.adjustment_factor,
+            None => self.scale,
+        }
+        .max(settings.min_scale)
+        .min(settings.max_scale);
+
+        Ok(())
+    }
+
+    /// The configured mutation settings with the current scale applied
+    pub fn apply<'a>(&self, settings: &'a MutationSettings) -> Cow<'a, MutationSettings> {
+        if self.scale == 1.0 {
+            Cow::Borrowed(settings)
+        } else {
+            Cow::Owned(settings.scaled(self.scale))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(rule: AdaptationRule, window: usize) -> AdaptiveMutationSettings {
+        AdaptiveMutationSettings {
+            rule,
+            window,
+            adjustment_factor: 2.0,
+            min_scale: 0.25,
+            max_scale: 4.0,
+        }
+    }
+
+    fn scales(rule: AdaptationRule, window: usize, generations: &[[f64; 2]]) -> Vec<f64> {
+        let settings = settings(rule, window);
+        let mut controller = MutationController::default();
+
+        generations
+            .iter()
+            .map(|fitness| {
+                controller.update(fitness, &settings).unwrap();
+                controller.scale()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn stagnation_raises_mutation_until_success() {
+        let generations = [
+            [1.0, 0.0],
+            [1.0, 0.0],
+            [1.0, 0.0],
+            [1.0, 0.0],
+            [0.5, 0.0],
+            [1.0, 0.5],
+        ];
+
+        let scales = scales(AdaptationRule::Stagnation, 3, &generations);
+
+        // Raised once the window holds three failures, lowered by the better mean
+        assert_eq!(scales, vec![1.0, 1.0, 1.0, 2.0, 4.0, 2.0]);
+    }
+
+    #[test]
+    fn success_rule_follows_success_ratio() {
+        let rule = AdaptationRule::SuccessRule { target_ratio: 0.2 };
+        let generations = [[1.0, 0.0], [2.0, 0.0], [3.0, 0.0], [3.0, 0.0], [3.0, 0.0]];
+
+        let scales = scales(rule, 2, &generations);
+
+        assert_eq!(scales, vec![1.0, 1.0, 0.5, 0.25, 0.5]);
+    }
+
+    #[test]
+    fn scale_stays_within_bounds() {
+        let generations = [[1.0, 0.0]; 8];
+
+        let scales = scales(AdaptationRule::Stagnation, 1, &generations);
+
+        assert_eq!(scales, vec![1.0, 2.0, 4.0, 4.0, 4.0, 4.0, 4.0, 4.0]);
+    }
+}
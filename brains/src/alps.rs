@@ -0,0 +1,379 @@
+use crate::{
+    error::BrainsError,
+    gen::{self, Breeding, SelectionMethod, SpecimenWriter},
+    islands,
+};
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct AlpsSettings {
+    pub layers: usize,
+    /// Base unit of the layer age limits. The bottom layer is also replaced by random members
+    /// every this many generations.
+    pub age_gap: usize,
+    pub aging_scheme: AgingScheme,
+}
+
+impl Default for AlpsSettings {
+    fn default() -> Self {
+        AlpsSettings {
+            layers: 5,
+            age_gap: 10,
+            aging_scheme: AgingScheme::Polynomial,
+        }
+    }
+}
+
+/// How the age limit grows from layer to layer, in multiples of the age gap. The top layer has no
+/// age limit.
+#[derive(Deserialize, Serialize, Clone, Copy)]
+pub enum AgingScheme {
+    /// 1, 2, 3, 4, ...
+    Linear,
+
+    /// 1, 4, 9, 16, ...
+    Polynomial,
+
+    /// 1, 2, 4, 8, ...
+    Exponential,
+}
+
+impl AlpsSettings {
+    pub fn validate(&self, population_size: usize) -> Result<(), BrainsError> {
+        // Every layer needs room for two parents
+        if self.layers == 0 || self.layers * 2 > population_size {
+            return Err(BrainsError::AlpsLayerCountInvalid);
+        }
+
+        if self.age_gap == 0 {
+            return Err(BrainsError::AlpsAgeGapZero);
+        }
+
+        Ok(())
+    }
+
+    /// Oldest age a member may have to stay in `layer`
+    pub fn age_limit(&self, layer: usize) -> usize {
+        if layer + 1 >= self.layers {
+            return usize::MAX;
+        }
+
+        let factor = match self.aging_scheme {
+            AgingScheme::Linear => layer + 1,
+            AgingScheme::Polynomial => (layer + 1) * (layer + 1),
+            AgingScheme::Exponential => 1 << layer,
+        };
+
+        self.age_gap.saturating_mul(factor)
+    }
+}
+
+/// Age and age layer of every member, empty if ALPS isn't used
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct AgeLayers {
+    ages: Vec<usize>,
+    layers: Vec<usize>,
+}
+
+impl AgeLayers {
+    /// Splits the population into equally sized layers from youngest to oldest, all of age 0
+    pub fn new(member_count: usize, settings: &AlpsSettings) -> AgeLayers {
+        AgeLayers {
+            ages: vec![0; member_count],
+            layers: islands::assign_islands(member_count, settings.layers),
+        }
+    }
+
+    pub fn is_assigned_to(&self, member_count: usize, settings: &AlpsSettings) -> bool {
+        self.ages.len() == member_count
+            && self.layers.len() == member_count
+            && self.layers.iter().all(|&l| l < settings.layers)
+    }
+}
+
+#[derive(Clone)]
+struct Aged<S> {
+    specimen: S,
+    age: usize,
+}
+
+/// Age-layered population structure: Every layer breeds from its own members and those of the
+/// layer below, as long as they aren't too old for it. Members that outgrow their layer can only
+/// survive by winning a place in the layer above. Every `age_gap` generations, the bottom layer is
+/// replaced by fresh members from `random_member`. Offspring are as old as their oldest parent,
+/// and everyone ages by one every generation. The elite of every layer is its share of
+/// `breeding.elitism`. The returned population is ordered by layer, and `age_layers` is updated
+/// to match once evolution succeeded.
+pub fn evolve_alps<R, S, C, CS, M, MS, N>(
+    rng: &mut R,
+    population: &[S],
+    fitness: &[f64],
+    age_layers: &mut AgeLayers,
+    settings: &AlpsSettings,
+    breeding: &Breeding<C, CS, M, MS>,
+    random_member: N,
+) -> Result<Vec<S>, BrainsError>
+where
+    S: Clone,
+    R: Rng + ?Sized,
+    C: Fn(&mut R, &[&S], &[f64], &mut SpecimenWriter<S>, &CS, &mut Vec<usize>),
+    M: Fn(&mut R, &mut S, &MS),
+    N: Fn(&mut R) -> S,
+{
+    assert_eq!(population.len(), fitness.len());
+
+    gen::validate_fitness(fitness)?;
+
+    let reassigned;
+    let current = if age_layers.is_assigned_to(population.len(), settings) {
+        &*age_layers
+    } else {
+        reassigned = AgeLayers::new(population.len(), settings);
+        &reassigned
+    };
+
+    let layer_sizes = islands::assign_islands(population.len(), settings.layers)
+        .into_iter()
+        .fold(vec![0; settings.layers], |mut sizes, layer| {
+            sizes[layer] += 1;
+            sizes
+        });
+
+    let aged_crossover = |rng: &mut R,
+                          input: &[&Aged<S>],
+                          input_fitness: &[f64],
+                          output: &mut SpecimenWriter<Aged<S>>,
+                          crossover_settings: &CS,
+                          buffer: &mut Vec<usize>| {
+        let parents = input.iter().map(|a| &a.specimen).collect::<Vec<_>>();
+        let age = input.iter().map(|a| a.age).max().unwrap_or(0);
+
+        let mut children = Vec::new();
+        (breeding.crossover)(
+            rng,
+            &parents,
+            input_fitness,
+            &mut SpecimenWriter::new(usize::MAX, &mut children),
+            crossover_settings,
+            buffer,
+        );
+
+        for specimen in children {
+            output.write(Aged { specimen, age });
+        }
+    };
+
+    let aged_mutate = |rng: &mut R, aged: &mut Aged<S>, mutate_settings: &MS| {
+        (breeding.mutate)(rng, &mut aged.specimen, mutate_settings)
+    };
+
+    let generation = breeding.generation;
+    let refill_bottom = generation > 0 && generation % settings.age_gap == 0;
+
+    let mut output = Vec::with_capacity(population.len());
+    let mut output_ages = Vec::with_capacity(population.len());
+
+    for (layer, &layer_size) in layer_sizes.iter().enumerate() {
+        let limit = settings.age_limit(layer);
+
+        // Everyone aged by one since they were evaluated
+        let (pool, pool_fitness): (Vec<_>, Vec<_>) = population
+            .iter()
+            .zip(fitness)
+            .zip(current.ages.iter().zip(&current.layers))
+            .filter(|(_, (&age, &member_layer))| {
+                (member_layer == layer || member_layer + 1 == layer) && age < limit
+            })
+            .map(|((specimen, &f), (&age, _))| {
+                (
+                    Aged {
+                        specimen: specimen.clone(),
+                        age: age + 1,
+                    },
+                    f,
+                )
+            })
+            .unzip();
+
+        let next_layer = if (layer == 0 && refill_bottom) || pool.is_empty() {
+            (0..layer_size)
+                .map(|_| Aged {
+                    specimen: random_member(rng),
+                    age: 0,
+                })
+                .collect()
+        } else {
+            // A fresh bottom layer often has no fitness at all yet
+            let selection_method = if pool_fitness.iter().all(|&f| f == 0.0) {
+                SelectionMethod::Uniform
+            } else {
+                breeding.selection_method
+            };
+
+            let layer_breeding = Breeding {
+                selection_method,
+                generation,
+                elitism: breeding.elitism * layer_size / population.len(),
+                crossover_inputs: breeding.crossover_inputs,
+                crossover: &aged_crossover,
+                crossover_settings: breeding.crossover_settings,
+                mutate: &aged_mutate,
+                mutate_settings: breeding.mutate_settings,
+            };
+
+            let mut next_layer = Vec::with_capacity(layer_size);
+            let mut writer = SpecimenWriter::new(layer_size, &mut next_layer);
+
+            gen::add_elitism_members(&mut writer, &pool, &pool_fitness, layer_breeding.elitism);
+            gen::breed(rng, &pool, &pool_fitness, &layer_breeding, &mut writer)?;
+
+            let elite_count = layer_breeding.elitism.min(pool.len()).min(layer_size);
+            next_layer
+                .iter_mut()
+                .skip(elite_count)
+                .for_each(|a| layer_breeding.mutate(rng, a));
+
+            next_layer
+        };
+
+        for aged in next_layer {
+            output.push(aged.specimen);
+            output_ages.push(aged.age);
+        }
+    }
+
+    *age_layers = AgeLayers {
+        ages: output_ages,
+        layers: islands::assign_islands(output.len(), settings.layers),
+    };
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen::tests::marking_breeding;
+    use rand::rngs::StdRng;
+
+    fn settings() -> AlpsSettings {
+        AlpsSettings {
+            layers: 2,
+            age_gap: 3,
+            aging_scheme: AgingScheme::Linear,
+        }
+    }
+
+    fn age_layers(ages: Vec<usize>) -> AgeLayers {
+        AgeLayers {
+            layers: islands::assign_islands(ages.len(), 2),
+            ages,
+        }
+    }
+
+    fn evolve(
+        fitness: &[f64],
+        age_layers: &mut AgeLayers,
+        selection_method: SelectionMethod,
+        generation: usize,
+    ) -> Result<Vec<f64>, BrainsError> {
+        let mut rng = StdRng::seed_from_u64(1);
+
+        evolve_alps(
+            &mut rng,
+            &[1.0, 2.0, 3.0, 4.0],
+            fitness,
+            age_layers,
+            &settings(),
+            &Breeding {
+                generation,
+                ..marking_breeding(selection_method, 0)
+            },
+            |_| -1.0,
+        )
+    }
+
+    #[test]
+    fn members_too_old_for_a_layer_leave_it() {
+        let mut layers = age_layers(vec![0, 3, 0, 5]);
+
+        let next = evolve(
+            &[1.0, 2.0, 3.0, 4.0],
+            &mut layers,
+            SelectionMethod::FitnessProportionate,
+            1,
+        )
+        .unwrap();
+
+        // The second member reached the bottom layer's age limit, so only the first one breeds
+        assert_eq!(next[..2], [11.0, 11.0]);
+        assert!(next[2..]
+            .iter()
+            .all(|n| [11.0, 12.0, 13.0, 14.0].contains(n)));
+
+        assert_eq!(layers.ages[..2], [1, 1]);
+        assert!(layers.ages[2..].iter().all(|a| (1..=6).contains(a)));
+        assert_eq!(layers.layers, vec![0, 0, 1, 1]);
+    }
+
+    #[test]
+    fn bottom_layer_is_refilled_every_age_gap() {
+        let mut layers = age_layers(vec![2, 2, 4, 4]);
+
+        let next = evolve(
+            &[1.0, 2.0, 3.0, 4.0],
+            &mut layers,
+            SelectionMethod::FitnessProportionate,
+            3,
+        )
+        .unwrap();
+
+        assert_eq!(next.len(), 4);
+        assert_eq!(next[..2], [-1.0, -1.0]);
+        assert_eq!(layers.ages[..2], [0, 0]);
+        assert_eq!(layers.ages[2..], [5, 5]);
+    }
+
+    #[test]
+    fn layers_without_fitness_breed_uniformly() {
+        let mut layers = age_layers(vec![0; 4]);
+
+        let next = evolve(
+            &[0.0, 0.0, 1.0, 1.0],
+            &mut layers,
+            SelectionMethod::FitnessProportionate,
+            1,
+        )
+        .unwrap();
+
+        assert!(next[..2].iter().all(|n| [11.0, 12.0].contains(n)));
+    }
+
+    #[test]
+    fn failed_evolution_keeps_age_layers() {
+        let mut layers = age_layers(vec![0, 1, 2, 3]);
+
+        let result = evolve(
+            &[-1.0, -2.0, -3.0, -4.0],
+            &mut layers,
+            SelectionMethod::FitnessProportionate,
+            1,
+        );
+
+        assert!(matches!(result, Err(BrainsError::NegativeFitness)));
+        assert_eq!(layers.ages, vec![0, 1, 2, 3]);
+        assert_eq!(layers.layers, vec![0, 0, 1, 1]);
+
+        let mut unassigned = AgeLayers::default();
+        let result = evolve(
+            &[-1.0, -2.0, -3.0, -4.0],
+            &mut unassigned,
+            SelectionMethod::FitnessProportionate,
+            1,
+        );
+
+        assert!(result.is_err());
+        assert!(unassigned.ages.is_empty() && unassigned.layers.is_empty());
+    }
+}
@@ -0,0 +1,474 @@
+use crate::{error::BrainsError, gen, nn::NeuralNetwork};
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct CmaEsSettings {
+    /// Step size of the first generation, in weight units
+    pub initial_sigma: f64,
+}
+
+impl Default for CmaEsSettings {
+    fn default() -> Self {
+        CmaEsSettings { initial_sigma: 0.5 }
+    }
+}
+
+impl CmaEsSettings {
+    pub fn validate(&self, population_size: usize) -> Result<(), BrainsError> {
+        if !self.initial_sigma.is_finite() || self.initial_sigma <= 0.0 {
+            return Err(BrainsError::CmaEsInvalidSigma);
+        }
+
+        // Needs at least one parent, which is half of the population
+        if population_size < 2 {
+            return Err(BrainsError::CmaEsPopulationTooSmall);
+        }
+
+        Ok(())
+    }
+}
+
+/// State of the covariance matrix adaptation evolution strategy, working on flattened weight
+/// vectors. Every generation is sampled from a multivariate normal distribution, whose mean,
+/// step size and covariance are moved towards the fittest samples of the previous generation.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct CmaEs {
+    mean: Vec<f64>,
+    sigma: f64,
+    /// Row-major, dimension x dimension
+    covariance: Vec<f64>,
+    /// Eigenvectors of the covariance as columns of a row-major matrix (B)
+    eigenvectors: Vec<f64>,
+    /// Square roots of the covariance eigenvalues (D)
+    axis_lengths: Vec<f64>,
+    /// Evolution path of the covariance (p_c)
+    covariance_path: Vec<f64>,
+    /// Conjugate evolution path of the step size (p_sigma)
+    sigma_path: Vec<f64>,
+    generation: usize,
+    /// Generation of the last eigen decomposition
+    decomposed_at: usize,
+}
+
+/// Strategy parameters, which only depend on the dimension and the number of samples
+struct Parameters {
+    weights: Vec<f64>,
+    mu_eff: f64,
+    c_sigma: f64,
+    d_sigma: f64,
+    c_c: f64,
+    c_1: f64,
+    c_mu: f64,
+    /// Expected length of a standard normally distributed vector
+    expected_norm: f64,
+}
+
+impl Parameters {
+    fn new(dimension: usize, samples: usize) -> Parameters {
+        let n = dimension as f64;
+        let mu = samples / 2;
+
+        let weights = (1..=mu)
+            .map(|i| (mu as f64 + 0.5).ln() - (i as f64).ln())
+            .collect::<Vec<_>>();
+        let weight_sum = weights.iter().sum::<f64>();
+        let weights = weights.iter().map(|w| w / weight_sum).collect::<Vec<_>>();
+        let mu_eff = 1.0 / weights.iter().map(|w| w * w).sum::<f64>();
+
+        let c_sigma = (mu_eff + 2.0) / (n + mu_eff + 5.0);
+        let d_sigma = 1.0 + 2.0 * (((mu_eff - 1.0) / (n + 1.0)).sqrt() - 1.0).max(0.0) + c_sigma;
+        let c_c = (4.0 + mu_eff / n) / (n + 4.0 + 2.0 * mu_eff / n);
+        let c_1 = 2.0 / ((n + 1.3).powi(2) + mu_eff);
+        let c_mu =
+            (1.0 - c_1).min(2.0 * (mu_eff - 2.0 + 1.0 / mu_eff) / ((n + 2.0).powi(2) + mu_eff));
+
+        Parameters {
+            weights,
+            mu_eff,
+            c_sigma,
+            d_sigma,
+            c_c,
+            c_1,
+            c_mu,
+            expected_norm: n.sqrt() * (1.0 - 1.0 / (4.0 * n) + 1.0 / (21.0 * n * n)),
+        }
+    }
+}
+
+impl CmaEs {
+    pub fn new(mean: Vec<f64>, settings: &CmaEsSettings) -> CmaEs {
+        let n = mean.len();
+
+        CmaEs {
+            mean,
+            sigma: settings.initial_sigma,
+            covariance: identity(n),
+            eigenvectors: identity(n),
+            axis_lengths: vec![1.0; n],
+            covariance_path: vec![0.0; n],
+            sigma_path: vec![0.0; n],
+            generation: 0,
+            decomposed_at: 0,
+        }
+    }
+
+    /// Starts the search at the mean of all member weights
+    pub fn from_population(population: &[NeuralNetwork], settings: &CmaEsSettings) -> CmaEs {
+        let mut mean = vec![0.0; population[0].total_weights()];
+
+        for nn in population {
+            for (m, w) in mean.iter_mut().zip(nn.flattened_weights()) {
+                *m += w / population.len() as f64;
+            }
+        }
+
+        CmaEs::new(mean, settings)
+    }
+
+    pub fn dimension(&self) -> usize {
+        self.mean.len()
+    }
+
+    pub fn mean(&self) -> &[f64] {
+        &self.mean[..]
+    }
+
+    pub fn sigma(&self) -> f64 {
+        self.sigma
+    }
+
+    /// Draws `count` weight vectors from the current search distribution
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R, count: usize) -> Vec<Vec<f64>> {
+        let n = self.dimension();
+
+        (0..count)
+            .map(|_| {
+                let scaled = self
+                    .axis_lengths
+                    .iter()
+                    .map(|d| d * gen::standard_normal(rng))
+                    .collect::<Vec<_>>();
+
+                (0..n)
+                    .map(|i| {
+                        let row = &self.eigenvectors[i * n..(i + 1) * n];
+                        let step = row.iter().zip(&scaled).map(|(b, s)| b * s).sum::<f64>();
+
+                        self.mean[i] + self.sigma * step
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Moves the search distribution towards the fittest of the evaluated `samples`
+    pub fn update(&mut self, samples: &[Vec<f64>], fitness: &[f64]) -> Result<(), BrainsError> {
+        assert_eq!(samples.len(), fitness.len());
+
+        // Needs at least one parent, which is half of the samples
+        if samples.len() < 2 {
+            return Err(BrainsError::CmaEsPopulationTooSmall);
+        }
+
+        gen::validate_fitness(fitness)?;
+
+        let n = self.dimension();
+        let p = Parameters::new(n, samples.len());
+
+        // Steps of the fittest samples, relative to the old mean and step size
+        let steps = gen::rank_by_fitness(fitness)
+            .into_iter()
+            .take(p.weights.len())
+            .map(|idx| {
+                samples[idx]
+                    .iter()
+                    .zip(&self.mean)
+                    .map(|(x, m)| (x - m) / self.sigma)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let mut weighted_step = vec![0.0; n];
+        for (w, step) in p.weights.iter().zip(&steps) {
+            for (ws, s) in weighted_step.iter_mut().zip(step) {
+                *ws += w * s;
+            }
+        }
+
+        for (m, ws) in self.mean.iter_mut().zip(&weighted_step) {
+            *m += self.sigma * ws;
+        }
+
+        let whitened = self.inverse_sqrt_covariance_times(&weighted_step);
+        let sigma_path_factor = (p.c_sigma * (2.0 - p.c_sigma) * p.mu_eff).sqrt();
+        for (ps, w) in self.sigma_path.iter_mut().zip(&whitened) {
+            *ps = (1.0 - p.c_sigma) * *ps + sigma_path_factor * w;
+        }
+
+        let sigma_path_norm = self.sigma_path.iter().map(|v| v * v).sum::<f64>().sqrt();
+        let stalled = sigma_path_norm
+            / (1.0 - (1.0 - p.c_sigma).powi(2 * (self.generation as i32 + 1))).sqrt()
+            >= (1.4 + 2.0 / (n as f64 + 1.0)) * p.expected_norm;
+
+        let covariance_path_factor = if stalled {
+            0.0
+        } else {
+            (p.c_c * (2.0 - p.c_c) * p.mu_eff).sqrt()
+        };
+        for (pc, ws) in self.covariance_path.iter_mut().zip(&weighted_step) {
+            *pc = (1.0 - p.c_c) * *pc + covariance_path_factor * ws;
+        }
+
+        let correction = if stalled { p.c_c * (2.0 - p.c_c) } else { 0.0 };
+        let decay = 1.0 - p.c_1 - p.c_mu + p.c_1 * correction;
+
+        for i in 0..n {
+            for j in 0..=i {
+                let rank_mu = p
+                    .weights
+                    .iter()
+                    .zip(&steps)
+                    .map(|(w, s)| w * s[i] * s[j])
+                    .sum::<f64>();
+
+                let c = decay * self.covariance[i * n + j]
+                    + p.c_1 * self.covariance_path[i] * self.covariance_path[j]
+                    + p.c_mu * rank_mu;
+
+                self.covariance[i * n + j] = c;
+                self.covariance[j * n + i] = c;
+            }
+        }
+
+        self.sigma *= ((p.c_sigma / p.d_sigma) * (sigma_path_norm / p.expected_norm - 1.0)).exp();
+        self.generation += 1;
+
+        // Decomposing is O(n^3), so it is only done as often as the covariance changes noticeably
+        let interval =
+            ((samples.len() as f64 / ((p.c_1 + p.c_mu) * n as f64 * 10.0)) as usize).max(1);
+        if self.generation - self.decomposed_at >= interval {
+            self.decompose();
+        }
+
+        Ok(())
+    }
+
+    /// C^(-1/2) * v = B * D^(-1) * B^T * v
+    fn inverse_sqrt_covariance_times(&self, v: &[f64]) -> Vec<f64> {
+        let n = self.dimension();
+
+        let projected = (0..n)
+            .map(|j| {
+                (0..n)
+                    .map(|i| self.eigenvectors[i * n + j] * v[i])
+                    .sum::<f64>()
+                    / self.axis_lengths[j]
+            })
+            .collect::<Vec<_>>();
+
+        (0..n)
+            .map(|i| {
+                self.eigenvectors[i * n..(i + 1) * n]
+                    .iter()
+                    .zip(&projected)
+                    .map(|(b, p)| b * p)
+                    .sum()
+            })
+            .collect()
+    }
+
+    fn decompose(&mut self) {
+        let (eigenvalues, eigenvectors) = symmetric_eigen(&self.covariance, self.dimension());
+
+        self.axis_lengths = eigenvalues
+            .iter()
+            .map(|&e| e.max(f64::MIN_POSITIVE).sqrt())
+            .collect();
+        self.eigenvectors = eigenvectors;
+        self.decomposed_at = self.generation;
+    }
+}
+
+/// Replaces the population by samples of the CMA-ES search distribution, after updating the
+/// distribution with the fitness of the current members
+pub fn evolve_cma_es<R: Rng + ?Sized>(
+    rng: &mut R,
+    population: &[NeuralNetwork],
+    fitness: &[f64],
+    state: &mut CmaEs,
+    offspring_count: usize,
+) -> Result<Vec<NeuralNetwork>, BrainsError> {
+    let samples = population
+        .iter()
+        .map(|nn| nn.flattened_weights())
+        .collect::<Vec<_>>();
+
+    state.update(&samples, fitness)?;
+
+    Ok(state
+        .sample(rng, offspring_count)
+        .into_iter()
+        .map(|weights| {
+            let mut nn = population[0].clone();
+            nn.set_flattened_weights(&weights);
+            nn
+        })
+        .collect())
+}
+
+fn identity(n: usize) -> Vec<f64> {
+    let mut matrix = vec![0.0; n * n];
+    for i in 0..n {
+        matrix[i * n + i] = 1.0;
+    }
+    matrix
+}
+
+/// Eigen decomposition of a symmetric, row-major matrix using the cyclic Jacobi method. Returns
+/// the eigenvalues and the matching eigenvectors as columns of a row-major matrix.
+fn symmetric_eigen(matrix: &[f64], n: usize) -> (Vec<f64>, Vec<f64>) {
+    const MAX_SWEEPS: usize = 100;
+
+    let mut a = matrix.to_vec();
+    let mut v = identity(n);
+
+    let norm = a.iter().map(|x| x * x).sum::<f64>();
+
+    for _ in 0..MAX_SWEEPS {
+        let off_diagonal = (0..n)
+            .flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+            .map(|(i, j)| a[i * n + j] * a[i * n + j])
+            .sum::<f64>();
+
+        if off_diagonal <= 1e-24 * norm {
+            break;
+        }
+
+        for p in 0..n {
+            for q in p + 1..n {
+                let apq = a[p * n + q];
+                if apq == 0.0 {
+                    continue;
+                }
+
+                let theta = (a[q * n + q] - a[p * n + p]) / (2.0 * apq);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+
+                for k in 0..n {
+                    let (akp, akq) = (a[k * n + p], a[k * n + q]);
+                    a[k * n + p] = c * akp - s * akq;
+                    a[k * n + q] = s * akp + c * akq;
+                }
+
+                for k in 0..n {
+                    let (apk, aqk) = (a[p * n + k], a[q * n + k]);
+                    a[p * n + k] = c * apk - s * aqk;
+                    a[q * n + k] = s * apk + c * aqk;
+                }
+
+                for k in 0..n {
+                    let (vkp, vkq) = (v[k * n + p], v[k * n + q]);
+                    v[k * n + p] = c * vkp - s * vkq;
+                    v[k * n + q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+
+    ((0..n).map(|i| a[i * n + i]).collect(), v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+
+    fn settings() -> CmaEsSettings {
+        CmaEsSettings { initial_sigma: 0.5 }
+    }
+
+    /// Runs `generations` of sampling and updating, maximizing `fitness`
+    fn optimize<F: Fn(&[f64]) -> f64>(
+        state: &mut CmaEs,
+        samples: usize,
+        generations: usize,
+        fitness: F,
+    ) {
+        let mut rng = StdRng::seed_from_u64(7);
+
+        for _ in 0..generations {
+            let samples = state.sample(&mut rng, samples);
+            let fitness = samples.iter().map(|s| fitness(s)).collect::<Vec<_>>();
+
+            state.update(&samples, &fitness).unwrap();
+        }
+    }
+
+    #[test]
+    fn eigen_decomposition_reconstructs_matrix() {
+        let matrix = [4.0, 1.0, -2.0, 1.0, 3.0, 0.5, -2.0, 0.5, 5.0];
+        let n = 3;
+
+        let (eigenvalues, eigenvectors) = symmetric_eigen(&matrix, n);
+
+        for i in 0..n {
+            for j in 0..n {
+                let reconstructed = (0..n)
+                    .map(|k| eigenvectors[i * n + k] * eigenvalues[k] * eigenvectors[j * n + k])
+                    .sum::<f64>();
+                let orthogonality = (0..n)
+                    .map(|k| eigenvectors[k * n + i] * eigenvectors[k * n + j])
+                    .sum::<f64>();
+
+                assert!((reconstructed - matrix[i * n + j]).abs() < 1e-9);
+                assert!((orthogonality - if i == j { 1.0 } else { 0.0 }).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn converges_on_sphere() {
+        let mut state = CmaEs::new(vec![1.0; 4], &settings());
+
+        optimize(&mut state, 12, 300, |x| {
+            -x.iter().map(|v| v * v).sum::<f64>()
+        });
+
+        assert!(state.mean().iter().all(|m| m.abs() < 1e-3));
+        assert!(state.sigma() < 0.01);
+    }
+
+    #[test]
+    fn step_size_grows_on_slope() {
+        let mut state = CmaEs::new(vec![0.0; 4], &settings());
+
+        optimize(&mut state, 12, 20, |x| x.iter().sum());
+
+        assert!(state.sigma() > settings().initial_sigma);
+        assert!(state.mean().iter().all(|&m| m > 0.0));
+    }
+
+    #[test]
+    fn covariance_stretches_along_fitness_gradient() {
+        let mut state = CmaEs::new(vec![0.0; 2], &settings());
+
+        optimize(&mut state, 8, 10, |x| x[0]);
+
+        // Only the first weight affects fitness, so only its variance is raised
+        assert!(state.covariance[0] > 1.0);
+        assert!(state.covariance[0] > 2.0 * state.covariance[3]);
+    }
+
+    #[test]
+    fn update_needs_two_samples() {
+        let mut state = CmaEs::new(vec![0.0; 2], &settings());
+
+        let result = state.update(&[vec![1.0, 1.0]], &[1.0]);
+
+        assert!(matches!(result, Err(BrainsError::CmaEsPopulationTooSmall)));
+        assert_eq!(state.mean(), &[0.0, 0.0]);
+    }
+}
@@ -1,8 +1,15 @@
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    adaptive_mutation::AdaptiveMutationSettings,
+    alps::AlpsSettings,
+    cma_es::CmaEsSettings,
+    differential_evolution::DifferentialEvolutionSettings,
     error::BrainsError,
-    gen::SelectionMethod,
+    gen::{FitnessShaping, GenerationScheme, SelectionMethod, TemperatureSchedule},
+    hall_of_fame::HallOfFameSettings,
+    islands::IslandSettings,
+    map_elites::MapElitesSettings,
     nn::{
         gen::{
             CrossoverSettings, CrossoverSettingsTemplate, MutationSettings,
@@ -10,8 +17,39 @@ use crate::{
         },
         NeuralNetwork, NeuralNetworkTemplate,
     },
+    novelty::NoveltySettings,
+    openai_es::OpenAiEsSettings,
+    speciation::SpeciationSettings,
 };
 
+/// What produces the members of the next generation
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub enum Optimizer {
+    /// Selection, crossover and mutation, shaped by the rest of the config
+    #[default]
+    GeneticAlgorithm,
+
+    /// Covariance matrix adaptation evolution strategy on the flattened weights. Doesn't use the
+    /// crossover, mutation and selection settings.
+    CmaEs(CmaEsSettings),
+
+    /// OpenAI style natural evolution strategy with mirrored sampling around a central network.
+    /// Needs an even population size and doesn't use the crossover, mutation and selection
+    /// settings.
+    OpenAiEs(OpenAiEsSettings),
+
+    /// Differential evolution on the flattened weights. The members are trials, which only
+    /// replace the target at the same index if they are at least as fit. Fitness shaping doesn't
+    /// apply, because fitness is compared across generations.
+    DifferentialEvolution(DifferentialEvolutionSettings),
+}
+
+impl Optimizer {
+    pub fn is_genetic_algorithm(&self) -> bool {
+        matches!(self, Optimizer::GeneticAlgorithm)
+    }
+}
+
 #[derive(Deserialize, Serialize, Clone)]
 pub struct ConfigTemplate {
     pub population_size: usize,
@@ -20,6 +58,26 @@ pub struct ConfigTemplate {
     pub elitism: f64,
     pub network: NeuralNetworkTemplate,
     pub selection_method: SelectionMethod,
+    #[serde(default)]
+    pub optimizer: Optimizer,
+    #[serde(default)]
+    pub fitness_shaping: FitnessShaping,
+    #[serde(default)]
+    pub generation_scheme: GenerationScheme,
+    #[serde(default)]
+    pub speciation: Option<SpeciationSettings>,
+    #[serde(default)]
+    pub islands: Option<IslandSettings>,
+    #[serde(default)]
+    pub alps: Option<AlpsSettings>,
+    #[serde(default)]
+    pub novelty: Option<NoveltySettings>,
+    #[serde(default)]
+    pub map_elites: Option<MapElitesSettings>,
+    #[serde(default)]
+    pub hall_of_fame: Option<HallOfFameSettings>,
+    #[serde(default)]
+    pub adaptive_mutation: Option<AdaptiveMutationSettings>,
     pub crossover: CrossoverSettingsTemplate,
     pub mutation: MutationSettingsTemplate,
 }
@@ -33,6 +91,16 @@ impl Default for ConfigTemplate {
             elitism: 0.05,
             network: Default::default(),
             selection_method: Default::default(),
+            optimizer: Default::default(),
+            fitness_shaping: Default::default(),
+            generation_scheme: Default::default(),
+            speciation: None,
+            islands: None,
+            alps: None,
+            novelty: None,
+            map_elites: None,
+            hall_of_fame: None,
+            adaptive_mutation: None,
             crossover: Default::default(),
             mutation: Default::default(),
         }
@@ -44,6 +112,16 @@ pub struct Config {
     crossover: CrossoverSettings,
     mutation: MutationSettings,
     selection_method: SelectionMethod,
+    optimizer: Optimizer,
+    fitness_shaping: FitnessShaping,
+    generation_scheme: GenerationScheme,
+    speciation: Option<SpeciationSettings>,
+    islands: Option<IslandSettings>,
+    alps: Option<AlpsSettings>,
+    novelty: Option<NoveltySettings>,
+    map_elites: Option<MapElitesSettings>,
+    hall_of_fame: Option<HallOfFameSettings>,
+    adaptive_mutation: Option<AdaptiveMutationSettings>,
     network: NeuralNetwork,
     template: ConfigTemplate,
 }
@@ -62,6 +140,136 @@ impl Config {
             return Err(BrainsError::InvalidElitismRatio);
         }
 
+        // Every crossover picks its parents from the population
+        if template.crossover.parents > template.population_size {
+            return Err(BrainsError::CrossoverInvalidParentCount);
+        }
+
+        Self::validate_selection_method(template.selection_method)?;
+
+        // These produce negative values, which proportionate selection can't handle
+        if template.optimizer.is_genetic_algorithm()
+            && matches!(
+                template.fitness_shaping,
+                FitnessShaping::ZScore | FitnessShaping::CenteredRank
+            )
+            && matches!(
+                template.selection_method,
+                SelectionMethod::FitnessProportionate | SelectionMethod::StochasticUniversal
+            )
+        {
+            return Err(BrainsError::FitnessShapingUnsupportedBySelection);
+        }
+
+        match template.generation_scheme {
+            GenerationScheme::MuPlusLambda { offspring: 0 }
+            | GenerationScheme::MuCommaLambda { offspring: 0 } => {
+                return Err(BrainsError::GenerationSchemeOffspringZero);
+            }
+            GenerationScheme::MuCommaLambda { offspring }
+                if offspring < template.population_size =>
+            {
+                return Err(BrainsError::GenerationSchemeTooFewOffspring);
+            }
+            _ => {}
+        }
+
+        if let Some(speciation) = &template.speciation {
+            speciation.validate()?;
+
+            match template.generation_scheme {
+                GenerationScheme::Generational => {}
+                _ => return Err(BrainsError::SpeciationRequiresGenerationalScheme),
+            }
+        }
+
+        if let Some(islands) = &template.islands {
+            islands.validate(template.population_size)?;
+
+            if template.speciation.is_some() {
+                return Err(BrainsError::IslandsWithSpeciation);
+            }
+
+            match template.generation_scheme {
+                GenerationScheme::Generational => {}
+                _ => return Err(BrainsError::IslandsRequireGenerationalScheme),
+            }
+        }
+
+        if let Some(alps) = &template.alps {
+            alps.validate(template.population_size)?;
+
+            if template.speciation.is_some() || template.islands.is_some() {
+                return Err(BrainsError::AlpsWithSpeciationOrIslands);
+            }
+
+            match template.generation_scheme {
+                GenerationScheme::Generational => {}
+                _ => return Err(BrainsError::AlpsRequiresGenerationalScheme),
+            }
+        }
+
+        if let Some(novelty) = &template.novelty {
+            novelty.validate()?;
+
+            // Reinjected members have a fitness, but no novelty score
+            if template.hall_of_fame.as_ref().map(|h| h.reinject) == Some(true) {
+                return Err(BrainsError::NoveltyWithHallOfFameReinjection);
+            }
+        }
+
+        if let Some(map_elites) = &template.map_elites {
+            map_elites.validate()?;
+        }
+
+        if let Some(hall_of_fame) = &template.hall_of_fame {
+            hall_of_fame.validate()?;
+
+            // Speciation, islands and ALPS need to know where every member of the breeding pool
+            // belongs
+            if hall_of_fame.reinject
+                && (template.speciation.is_some()
+                    || template.islands.is_some()
+                    || template.alps.is_some())
+            {
+                return Err(BrainsError::HallOfFameReinjectionUnsupported);
+            }
+        }
+
+        if let Some(adaptive_mutation) = &template.adaptive_mutation {
+            adaptive_mutation.validate()?;
+        }
+
+        match &template.optimizer {
+            Optimizer::GeneticAlgorithm => {}
+            Optimizer::CmaEs(settings) => settings.validate(template.population_size)?,
+            Optimizer::OpenAiEs(settings) => settings.validate(template.population_size)?,
+            Optimizer::DifferentialEvolution(settings) => {
+                settings.validate(template.population_size)?;
+
+                // Novelty is only comparable within one generation, but trials compete against
+                // targets from earlier ones
+                if template.novelty.is_some() {
+                    return Err(BrainsError::NoveltyWithDifferentialEvolution);
+                }
+            }
+        }
+
+        // Other optimizers replace the entire population themselves
+        if !template.optimizer.is_genetic_algorithm() {
+            let generational = matches!(template.generation_scheme, GenerationScheme::Generational);
+
+            if !generational
+                || template.speciation.is_some()
+                || template.islands.is_some()
+                || template.alps.is_some()
+                || template.adaptive_mutation.is_some()
+                || template.hall_of_fame.as_ref().map(|h| h.reinject) == Some(true)
+            {
+                return Err(BrainsError::OptimizerUnsupportedSettings);
+            }
+        }
+
         let elitism = (template.elitism * template.population_size as f64).trunc() as usize;
 
         let network = match NeuralNetwork::from_template(&template.network) {
@@ -82,6 +290,16 @@ impl Config {
         Ok(Config {
             elitism,
             selection_method: template.selection_method,
+            optimizer: template.optimizer.clone(),
+            fitness_shaping: template.fitness_shaping,
+            generation_scheme: template.generation_scheme,
+            speciation: template.speciation.clone(),
+            islands: template.islands.clone(),
+            alps: template.alps.clone(),
+            novelty: template.novelty.clone(),
+            map_elites: template.map_elites.clone(),
+            hall_of_fame: template.hall_of_fame.clone(),
+            adaptive_mutation: template.adaptive_mutation.clone(),
             crossover,
             mutation,
             network,
@@ -89,6 +307,53 @@ impl Config {
         })
     }
 
+    fn validate_selection_method(method: SelectionMethod) -> Result<(), BrainsError> {
+        match method {
+            SelectionMethod::Tournament(0)
+            | SelectionMethod::StochasticTournament { size: 0, .. } => {
+                Err(BrainsError::InvalidTournamentSize)
+            }
+            SelectionMethod::StochasticTournament {
+                winner_probability, ..
+            } if winner_probability <= 0.0 || winner_probability > 1.0 => {
+                Err(BrainsError::InvalidTournamentWinnerProbability)
+            }
+            SelectionMethod::Truncation(ratio) if ratio <= 0.0 || ratio > 1.0 => {
+                Err(BrainsError::InvalidTruncationRatio)
+            }
+            SelectionMethod::LinearRank { pressure } if !(1.0..=2.0).contains(&pressure) => {
+                Err(BrainsError::InvalidLinearRankPressure)
+            }
+            SelectionMethod::ExponentialRank { base } if base <= 0.0 || base >= 1.0 => {
+                Err(BrainsError::InvalidExponentialRankBase)
+            }
+            SelectionMethod::Boltzmann { temperature } => {
+                Self::validate_temperature_schedule(temperature)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn validate_temperature_schedule(schedule: TemperatureSchedule) -> Result<(), BrainsError> {
+        match schedule {
+            TemperatureSchedule::Constant(t) if t <= 0.0 => {
+                Err(BrainsError::InvalidBoltzmannTemperature)
+            }
+            TemperatureSchedule::LinearDecay { start, end, .. } if start <= 0.0 || end <= 0.0 => {
+                Err(BrainsError::InvalidBoltzmannTemperature)
+            }
+            TemperatureSchedule::ExponentialDecay { start, min, .. }
+                if start <= 0.0 || min <= 0.0 =>
+            {
+                Err(BrainsError::InvalidBoltzmannTemperature)
+            }
+            TemperatureSchedule::ExponentialDecay { rate, .. } if rate <= 0.0 || rate > 1.0 => {
+                Err(BrainsError::InvalidBoltzmannDecayRate)
+            }
+            _ => Ok(()),
+        }
+    }
+
     pub fn elitism(&self) -> usize {
         self.elitism
     }
@@ -97,6 +362,67 @@ impl Config {
         self.selection_method
     }
 
+    pub fn optimizer(&self) -> &Optimizer {
+        &self.optimizer
+    }
+
+    pub fn cma_es(&self) -> Option<&CmaEsSettings> {
+        match &self.optimizer {
+            Optimizer::CmaEs(settings) => Some(settings),
+            _ => None,
+        }
+    }
+
+    pub fn openai_es(&self) -> Option<&OpenAiEsSettings> {
+        match &self.optimizer {
+            Optimizer::OpenAiEs(settings) => Some(settings),
+            _ => None,
+        }
+    }
+
+    pub fn differential_evolution(&self) -> Option<&DifferentialEvolutionSettings> {
+        match &self.optimizer {
+            Optimizer::DifferentialEvolution(settings) => Some(settings),
+            _ => None,
+        }
+    }
+
+    pub fn fitness_shaping(&self) -> FitnessShaping {
+        self.fitness_shaping
+    }
+
+    pub fn generation_scheme(&self) -> GenerationScheme {
+        self.generation_scheme
+    }
+
+    pub fn speciation(&self) -> Option<&SpeciationSettings> {
+        self.speciation.as_ref()
+    }
+
+    pub fn islands(&self) -> Option<&IslandSettings> {
+        self.islands.as_ref()
+    }
+
+    pub fn alps(&self) -> Option<&AlpsSettings> {
+        self.alps.as_ref()
+    }
+
+    pub fn novelty(&self) -> Option<&NoveltySettings> {
+        self.novelty.as_ref()
+    }
+
+    pub fn map_elites(&self) -> Option<&MapElitesSettings> {
+        self.map_elites.as_ref()
+    }
+
+    pub fn hall_of_fame(&self) -> Option<&HallOfFameSettings> {
+        self.hall_of_fame.as_ref()
+    }
+
+    pub fn adaptive_mutation(&self) -> Option<&AdaptiveMutationSettings> {
+        self.adaptive_mutation.as_ref()
+    }
+
     pub fn crossover_settings(&self) -> &CrossoverSettings {
         &self.crossover
     }
@@ -0,0 +1,280 @@
+use crate::{error::BrainsError, gen, nn::NeuralNetwork};
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct DifferentialEvolutionSettings {
+    pub strategy: DifferentialEvolutionStrategy,
+    /// Scale of the difference vector (F)
+    pub differential_weight: f64,
+    /// Probability of taking a weight from the donor instead of the target (CR)
+    pub crossover_rate: f64,
+}
+
+impl Default for DifferentialEvolutionSettings {
+    fn default() -> Self {
+        DifferentialEvolutionSettings {
+            strategy: DifferentialEvolutionStrategy::Rand1Bin,
+            differential_weight: 0.5,
+            crossover_rate: 0.9,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy)]
+pub enum DifferentialEvolutionStrategy {
+    /// DE/rand/1/bin: The donor is a random target plus the scaled difference of two others
+    Rand1Bin,
+
+    /// DE/best/1/bin: The donor is the fittest target plus the scaled difference of two others
+    Best1Bin,
+}
+
+impl DifferentialEvolutionSettings {
+    pub fn validate(&self, population_size: usize) -> Result<(), BrainsError> {
+        if !(self.differential_weight > 0.0 && self.differential_weight <= 2.0) {
+            return Err(BrainsError::DifferentialEvolutionInvalidWeight);
+        }
+
+        if !(0.0..=1.0).contains(&self.crossover_rate) {
+            return Err(BrainsError::DifferentialEvolutionInvalidCrossoverRate);
+        }
+
+        // Every target needs three distinct other targets for its donor
+        if population_size < 4 {
+            return Err(BrainsError::DifferentialEvolutionPopulationTooSmall);
+        }
+
+        Ok(())
+    }
+}
+
+/// Targets of differential evolution. The members of the population are the trials, every one
+/// of which competes only against the target at the same index once it was evaluated.
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct DifferentialEvolution {
+    targets: Vec<NeuralNetwork>,
+    target_fitness: Vec<f64>,
+    /// Whether the trial at every index replaced its target in the last selection. These are the
+    /// trials that were evaluated then, the current members are already the next ones.
+    replaced: Vec<bool>,
+}
+
+impl DifferentialEvolution {
+    pub fn targets(&self) -> &[NeuralNetwork] {
+        &self.targets[..]
+    }
+
+    pub fn target_fitness(&self) -> &[f64] {
+        &self.target_fitness[..]
+    }
+
+    pub fn replaced(&self) -> &[bool] {
+        &self.replaced[..]
+    }
+
+    /// Whether the targets fit networks shaped like `nn`. True as long as there are no targets.
+    pub fn matches(&self, nn: &NeuralNetwork) -> bool {
+        self.targets
+            .first()
+            .map(|t| t.is_structurally_equal(nn))
+            .unwrap_or(true)
+    }
+
+    /// Every trial that is at least as fit as its target replaces it. The very first population
+    /// has no targets to compete with and becomes the targets as a whole.
+    pub fn select(&mut self, trials: &[NeuralNetwork], fitness: &[f64]) -> Result<(), BrainsError> {
+        assert_eq!(trials.len(), fitness.len());
+
+        gen::validate_fitness(fitness)?;
+
+        if self.targets.len() != trials.len() {
+            self.targets = trials.to_vec();
+            self.target_fitness = fitness.to_vec();
+            self.replaced = vec![true; trials.len()];
+
+            return Ok(());
+        }
+
+        for (idx, (trial, &f)) in trials.iter().zip(fitness).enumerate() {
+            let replace = f >= self.target_fitness[idx];
+
+            if replace {
+                self.targets[idx] = trial.clone();
+                self.target_fitness[idx] = f;
+            }
+
+            self.replaced[idx] = replace;
+        }
+
+        Ok(())
+    }
+
+    /// Creates one trial per target through mutation with a difference vector and binomial
+    /// crossover with the target
+    pub fn trials<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        settings: &DifferentialEvolutionSettings,
+    ) -> Vec<NeuralNetwork> {
+        let best = gen::rank_by_fitness(&self.target_fitness)[0];
+
+        (0..self.targets.len())
+            .map(|idx| {
+                let others = rand::seq::index::sample(rng, self.targets.len() - 1, 3)
+                    .into_iter()
+                    .map(|o| if o >= idx { o + 1 } else { o })
+                    .collect::<Vec<_>>();
+
+                let (base, a, b) = match settings.strategy {
+                    DifferentialEvolutionStrategy::Rand1Bin => (others[0], others[1], others[2]),
+                    DifferentialEvolutionStrategy::Best1Bin => (best, others[0], others[1]),
+                };
+
+                trial(
+                    rng,
+                    &self.targets[idx],
+                    &self.targets[base],
+                    &self.targets[a],
+                    &self.targets[b],
+                    settings,
+                )
+            })
+            .collect()
+    }
+}
+
+/// target with every weight crossed over from base + F * (a - b) with probability CR, and at
+/// least one weight always crossed over
+fn trial<R: Rng + ?Sized>(
+    rng: &mut R,
+    target: &NeuralNetwork,
+    base: &NeuralNetwork,
+    a: &NeuralNetwork,
+    b: &NeuralNetwork,
+    settings: &DifferentialEvolutionSettings,
+) -> NeuralNetwork {
+    let mut trial = target.clone();
+    let forced = rng.gen_range(0, target.total_weights());
+
+    let (base, a, b) = (base.layers(), a.layers(), b.layers());
+    let mut idx = 0;
+
+    for (l, layer) in trial.layers_mut().iter_mut().enumerate() {
+        let base = base[l].all_weights();
+        let a = a[l].all_weights();
+        let b = b[l].all_weights();
+
+        for (w, weight) in layer.all_weights_mut().iter_mut().enumerate() {
+            if idx == forced || rng.gen_range(0.0, 1.0) < settings.crossover_rate {
+                *weight = base[w] + settings.differential_weight * (a[w] - b[w]);
+            }
+
+            idx += 1;
+        }
+    }
+
+    trial
+}
+
+/// Lets the evaluated trials compete against their targets, then creates the next trials
+pub fn evolve_differential_evolution<R: Rng + ?Sized>(
+    rng: &mut R,
+    trials: &[NeuralNetwork],
+    fitness: &[f64],
+    state: &mut DifferentialEvolution,
+    settings: &DifferentialEvolutionSettings,
+) -> Result<Vec<NeuralNetwork>, BrainsError> {
+    state.select(trials, fitness)?;
+
+    Ok(state.trials(rng, settings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nn::Activation;
+    use rand::rngs::StdRng;
+
+    fn network(weight: f64) -> NeuralNetwork {
+        let mut nn = NeuralNetwork::new(2, vec![vec![Activation::Linear; 3]]);
+        let weights = vec![weight; nn.total_weights()];
+        nn.set_flattened_weights(&weights);
+        nn
+    }
+
+    fn settings(crossover_rate: f64) -> DifferentialEvolutionSettings {
+        DifferentialEvolutionSettings {
+            strategy: DifferentialEvolutionStrategy::Rand1Bin,
+            differential_weight: 0.5,
+            crossover_rate,
+        }
+    }
+
+    #[test]
+    fn trials_replace_weaker_targets() {
+        let mut state = DifferentialEvolution::default();
+        let first = (0..4).map(|i| network(i as f64)).collect::<Vec<_>>();
+
+        state.select(&first, &[1.0, 2.0, 3.0, 4.0]).unwrap();
+
+        assert_eq!(state.replaced(), &[true; 4]);
+
+        let trials = (10..14).map(|i| network(i as f64)).collect::<Vec<_>>();
+        state.select(&trials, &[0.5, 2.0, 3.5, 1.0]).unwrap();
+
+        // Ties go to the trial
+        assert_eq!(state.replaced(), &[false, true, true, false]);
+        assert_eq!(state.target_fitness(), &[1.0, 2.0, 3.5, 4.0]);
+        assert_eq!(state.targets()[1].flattened_weights()[0], 11.0);
+        assert_eq!(state.targets()[3].flattened_weights()[0], 3.0);
+    }
+
+    #[test]
+    fn trials_cross_over_at_least_one_weight() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut state = DifferentialEvolution::default();
+        let targets = (0..5).map(|i| network(i as f64)).collect::<Vec<_>>();
+
+        state.select(&targets, &[1.0; 5]).unwrap();
+
+        for (idx, trial) in state.trials(&mut rng, &settings(0.0)).iter().enumerate() {
+            let changed = trial
+                .flattened_weights()
+                .iter()
+                .filter(|&&w| w != idx as f64)
+                .count();
+
+            assert_eq!(changed, 1);
+        }
+    }
+
+    #[test]
+    fn donors_come_from_other_targets() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let mut state = DifferentialEvolution::default();
+        let targets = (0..4).map(|i| network(i as f64)).collect::<Vec<_>>();
+
+        state.select(&targets, &[1.0; 4]).unwrap();
+
+        for (idx, trial) in state.trials(&mut rng, &settings(1.0)).iter().enumerate() {
+            let weights = trial.flattened_weights();
+            let others = (0..4).filter(|&o| o != idx).map(|o| o as f64);
+            let sum = others.clone().sum::<f64>();
+
+            // base + 0.5 * (a - b) for three distinct targets other than the own one
+            let possible = others
+                .clone()
+                .flat_map(|base| {
+                    let rest = sum - base;
+                    others
+                        .clone()
+                        .filter(move |&a| a != base)
+                        .map(move |a| base + 0.5 * (a - (rest - a)))
+                })
+                .collect::<Vec<_>>();
+
+            assert!(weights.iter().all(|w| possible.contains(w)));
+        }
+    }
+}
@@ -16,6 +16,50 @@ pub enum BrainsError {
     MinWeightLargerThanMaxWeight,
     ConfigPathNull,
     InvalidElitismRatio,
+    InvalidTournamentSize,
+    InvalidTournamentWinnerProbability,
+    InvalidTruncationRatio,
+    InvalidLinearRankPressure,
+    InvalidExponentialRankBase,
+    InvalidBoltzmannTemperature,
+    InvalidBoltzmannDecayRate,
+    GenerationSchemeOffspringZero,
+    GenerationSchemeTooFewOffspring,
+    SpeciationTargetSpeciesZero,
+    SpeciationInvalidThreshold,
+    SpeciationRequiresGenerationalScheme,
+    IslandCountInvalid,
+    IslandMigrationIntervalZero,
+    IslandMigrantsInvalid,
+    IslandsWithSpeciation,
+    IslandsRequireGenerationalScheme,
+    NoveltyNeighboursZero,
+    NoveltyInvalidFitnessWeight,
+    MapElitesNoDimensions,
+    MapElitesInvalidDimension,
+    HallOfFameSizeZero,
+    HallOfFameReinjectionUnsupported,
+    AdaptiveMutationWindowZero,
+    AdaptiveMutationInvalidFactor,
+    AdaptiveMutationInvalidScaleBounds,
+    AdaptiveMutationInvalidSuccessRatio,
+    CmaEsInvalidSigma,
+    CmaEsPopulationTooSmall,
+    OptimizerUnsupportedSettings,
+    OpenAiEsInvalidNoise,
+    OpenAiEsInvalidLearningRate,
+    OpenAiEsInvalidAdamDecay,
+    OpenAiEsPopulationNotEven,
+    DifferentialEvolutionInvalidWeight,
+    DifferentialEvolutionInvalidCrossoverRate,
+    DifferentialEvolutionPopulationTooSmall,
+    AlpsLayerCountInvalid,
+    AlpsAgeGapZero,
+    AlpsWithSpeciationOrIslands,
+    AlpsRequiresGenerationalScheme,
+    FitnessShapingUnsupportedBySelection,
+    NoveltyWithHallOfFameReinjection,
+    NoveltyWithDifferentialEvolution,
 
     // Neural network config
     NeuralNetworkConfigNoLayers = 300,
@@ -28,6 +72,12 @@ pub enum BrainsError {
     CrossoverInvalidMethodProbabilities,
     CrossoverEmptyMethodProbabilities,
     CrossoverInvalidSwapWeightsRatios,
+    CrossoverInvalidParentCount,
+    CrossoverInvalidArithmeticAlphas,
+    CrossoverInvalidBlendAlpha,
+    CrossoverInvalidSbxEta,
+    CrossoverInvalidSbxProbability,
+    CrossoverKPointCountZero,
 
     // Mutation config
     MutationInvalidProbability = 600,
@@ -38,16 +88,35 @@ pub enum BrainsError {
     MutationInvalidReplaceMethodMinMax,
     MutationInvalidScaleMethodMinMax,
     MutationInvalidShiftMethodMinMax,
+    MutationSelfAdaptiveMethodWithoutSettings,
+    MutationInvalidSelfAdaptation,
 
     // Evolution
     FitnessPointerNull = 700,
     MissingEvolutionConfig,
+    InvalidFitnessValue,
+    AllFitnessZero,
+    NegativeFitness,
+    InvalidReplacementCount,
+    ReplacedIndicesPointerNull,
+    DescriptorsPointerNull,
+    NoveltySearchDisabled,
+    BehaviorDescriptorLengthZero,
+    BehaviorDescriptorLengthMismatch,
+    ObjectivesPointerNull,
+    ObjectiveCountZero,
+    MapElitesDisabled,
+    MapElitesArchiveEmpty,
+    SampleCountZero,
+    UnsupportedByOptimizer,
+    SteadyStateUnsupportedSettings,
 
     // Export
     InvalidOutputPath = 800,
     ExportMemberCountZero,
     FileSaveError,
     OutputPathNull,
+    HallOfFameEmpty,
 
     // Import
     PopulationPathNull = 900,
@@ -62,4 +131,11 @@ pub enum BrainsError {
     InvalidMemberIndex = 1000,
     InputsPointerNull,
     OutputsPointerNull,
+    CountPointerNull,
+    SpeciationDisabled,
+    SpeciesIdsPointerNull,
+    MutationSigmasPointerNull,
+    EffectiveMutationPointerNull,
+    DifferentialEvolutionDisabled,
+    TrialReplacementsPointerNull,
 }
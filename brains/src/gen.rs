@@ -1,5 +1,6 @@
 use std::cmp::Ordering;
 
+use crate::error::BrainsError;
 use rand::{distributions::weighted::alias_method::WeightedIndex, prelude::*};
 use serde::{Deserialize, Serialize};
 
@@ -9,6 +10,10 @@ pub struct SpecimenWriter<'a, S> {
 }
 
 impl<'a, S> SpecimenWriter<'a, S> {
+    pub(crate) fn new(limit: usize, buffer: &'a mut Vec<S>) -> SpecimenWriter<'a, S> {
+        SpecimenWriter { limit, buffer }
+    }
+
     pub fn write(&mut self, specimen: S) {
         if self.can_write() {
             self.buffer.push(specimen);
@@ -20,26 +25,62 @@ impl<'a, S> SpecimenWriter<'a, S> {
     }
 }
 
+/// How children are made: parent selection, crossover and mutation, plus the number of fittest
+/// members that survive unchanged. Schemes that pick parents or survivors in their own way ignore
+/// the fields they have no use for.
+pub struct Breeding<'a, C, CS, M, MS> {
+    pub selection_method: SelectionMethod,
+    /// Current generation, for selection methods that change over time
+    pub generation: usize,
+    pub elitism: usize,
+    /// Number of parents per crossover
+    pub crossover_inputs: usize,
+    pub crossover: C,
+    pub crossover_settings: &'a CS,
+    pub mutate: M,
+    pub mutate_settings: &'a MS,
+}
+
+impl<'a, C, CS, M, MS> Breeding<'a, C, CS, M, MS> {
+    /// Borrows the closures, so single fields can be overridden for a part of the population
+    pub(crate) fn by_ref(&self) -> Breeding<'a, &C, CS, &M, MS> {
+        Breeding {
+            selection_method: self.selection_method,
+            generation: self.generation,
+            elitism: self.elitism,
+            crossover_inputs: self.crossover_inputs,
+            crossover: &self.crossover,
+            crossover_settings: self.crossover_settings,
+            mutate: &self.mutate,
+            mutate_settings: self.mutate_settings,
+        }
+    }
+
+    pub(crate) fn mutate<R, S>(&self, rng: &mut R, specimen: &mut S)
+    where
+        R: ?Sized,
+        M: Fn(&mut R, &mut S, &MS),
+    {
+        (self.mutate)(rng, specimen, self.mutate_settings)
+    }
+}
+
 pub fn evolve<R, S, C, CS, M, MS>(
     rng: &mut R,
     population: &[S],
     fitness: &[f64],
-    selection_method: SelectionMethod,
-    elitism: usize,
-    crossover_inputs: usize,
-    crossover: C,
-    crossover_settings: &CS,
-    mutate: M,
-    mutate_settings: &MS,
-) -> Vec<S>
+    breeding: &Breeding<C, CS, M, MS>,
+) -> Result<Vec<S>, BrainsError>
 where
     S: Clone,
     R: Rng + ?Sized,
-    C: Fn(&mut R, &[&S], &mut SpecimenWriter<S>, &CS, &mut Vec<usize>),
+    C: Fn(&mut R, &[&S], &[f64], &mut SpecimenWriter<S>, &CS, &mut Vec<usize>),
     M: Fn(&mut R, &mut S, &MS),
 {
     assert_eq!(population.len(), fitness.len());
 
+    validate_fitness(fitness)?;
+
     let mut output = Vec::with_capacity(population.len());
 
     let mut output_writer = SpecimenWriter {
@@ -47,51 +88,368 @@ where
         buffer: &mut output,
     };
 
-    add_elitism_members(&mut output_writer, population, fitness, elitism);
+    add_elitism_members(&mut output_writer, population, fitness, breeding.elitism);
+
+    breed(rng, population, fitness, breeding, &mut output_writer)?;
+
+    // mutation
+    output
+        .iter_mut()
+        .skip(breeding.elitism)
+        .for_each(|s| breeding.mutate(rng, s));
+
+    Ok(output)
+}
+
+/// Evolution strategy style generation: The best `parent_count` members become parents of
+/// `offspring_count` children. With `keep_parents`, the parents are carried over and compete with
+/// their children in the next round (mu + lambda), otherwise only the children remain
+/// (mu, lambda). The returned population starts with the parents, if they are kept.
+pub fn evolve_mu_lambda<R, S, C, CS, M, MS>(
+    rng: &mut R,
+    population: &[S],
+    fitness: &[f64],
+    parent_count: usize,
+    offspring_count: usize,
+    keep_parents: bool,
+    breeding: &Breeding<C, CS, M, MS>,
+) -> Result<Vec<S>, BrainsError>
+where
+    S: Clone,
+    R: Rng + ?Sized,
+    C: Fn(&mut R, &[&S], &[f64], &mut SpecimenWriter<S>, &CS, &mut Vec<usize>),
+    M: Fn(&mut R, &mut S, &MS),
+{
+    assert_eq!(population.len(), fitness.len());
+
+    validate_fitness(fitness)?;
+
+    let mut parents = Vec::with_capacity(parent_count);
+    let mut parent_fitness = Vec::with_capacity(parent_count);
+
+    for idx in rank_by_fitness(fitness).into_iter().take(parent_count) {
+        parents.push(population[idx].clone());
+        parent_fitness.push(fitness[idx]);
+    }
+
+    let mut offspring = Vec::with_capacity(offspring_count);
+
+    breed(
+        rng,
+        &parents,
+        &parent_fitness,
+        breeding,
+        &mut SpecimenWriter {
+            limit: offspring_count,
+            buffer: &mut offspring,
+        },
+    )?;
+
+    offspring.iter_mut().for_each(|s| breeding.mutate(rng, s));
+
+    if keep_parents {
+        parents.append(&mut offspring);
+        Ok(parents)
+    } else {
+        Ok(offspring)
+    }
+}
+
+/// Replaces only the `replace_count` weakest members of the population with offspring and writes
+/// their indices into `replaced`. Everything else stays untouched.
+pub fn evolve_steady_state<R, S, C, CS, M, MS>(
+    rng: &mut R,
+    population: &mut [S],
+    fitness: &[f64],
+    replace_count: usize,
+    breeding: &Breeding<C, CS, M, MS>,
+    replaced: &mut Vec<usize>,
+) -> Result<(), BrainsError>
+where
+    S: Clone,
+    R: Rng + ?Sized,
+    C: Fn(&mut R, &[&S], &[f64], &mut SpecimenWriter<S>, &CS, &mut Vec<usize>),
+    M: Fn(&mut R, &mut S, &MS),
+{
+    assert_eq!(population.len(), fitness.len());
+    assert!(replace_count <= population.len());
+
+    validate_fitness(fitness)?;
+
+    let mut offspring = Vec::with_capacity(replace_count);
+
+    breed(
+        rng,
+        population,
+        fitness,
+        breeding,
+        &mut SpecimenWriter {
+            limit: replace_count,
+            buffer: &mut offspring,
+        },
+    )?;
+
+    offspring.iter_mut().for_each(|s| breeding.mutate(rng, s));
+
+    replaced.clear();
+    replaced.extend(
+        rank_by_fitness(fitness)
+            .into_iter()
+            .rev()
+            .take(replace_count),
+    );
+
+    for (&idx, child) in replaced.iter().zip(offspring) {
+        population[idx] = child;
+    }
+
+    Ok(())
+}
+
+/// NSGA-II with multiple objectives, all of which are maximized. `objectives` holds
+/// `objective_count` values per member, back to back. `population` is made up of the last parents
+/// and their children, and the best `parent_count` of them by Pareto rank (ties broken by crowding
+/// distance) become the next parents. The returned population holds these parents, followed by as
+/// many children, whose parents are chosen by binary tournaments on the same criteria.
+pub fn evolve_nsga2<R, S, C, CS, M, MS>(
+    rng: &mut R,
+    population: &[S],
+    objectives: &[f64],
+    objective_count: usize,
+    parent_count: usize,
+    breeding: &Breeding<C, CS, M, MS>,
+) -> Result<Vec<S>, BrainsError>
+where
+    S: Clone,
+    R: Rng + ?Sized,
+    C: Fn(&mut R, &[&S], &[f64], &mut SpecimenWriter<S>, &CS, &mut Vec<usize>),
+    M: Fn(&mut R, &mut S, &MS),
+{
+    assert_eq!(population.len() * objective_count, objectives.len());
+
+    validate_fitness(objectives)?;
+
+    // Environmental selection: whole fronts first, the last one that fits only partially is cut
+    // by crowding distance
+    let mut sorted = Vec::with_capacity(population.len());
+
+    for front in non_dominated_sort(objectives, objective_count) {
+        let distance = crowding_distance(objectives, objective_count, &front);
+
+        let mut front = front.into_iter().zip(distance).collect::<Vec<_>>();
+        front.sort_unstable_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+
+        sorted.extend(front.into_iter().map(|(idx, _)| idx));
+    }
+
+    // Parents are in crowded comparison order, so a lower index is better
+    let parents = sorted
+        .into_iter()
+        .take(parent_count)
+        .map(|idx| population[idx].clone())
+        .collect::<Vec<_>>();
+
+    let mut output = Vec::with_capacity(parents.len() * 2);
+    let mut output_writer = SpecimenWriter::new(parents.len() * 2, &mut output);
+
+    for parent in &parents {
+        output_writer.write(parent.clone());
+    }
+
+    let mut crossover_input_buffer = Vec::with_capacity(breeding.crossover_inputs);
+    let mut crossover_fitness_buffer = Vec::with_capacity(breeding.crossover_inputs);
+    let mut crossover_weight_index_buffer = Vec::new();
+
+    while output_writer.can_write() {
+        crossover_input_buffer.clear();
+        crossover_fitness_buffer.clear();
+
+        for _ in 0..breeding.crossover_inputs {
+            let a = rng.gen_range(0, parents.len());
+            let b = rng.gen_range(0, parents.len());
+            let winner = a.min(b);
+
+            crossover_input_buffer.push(&parents[winner]);
+            // Crowded-comparison rank as fitness, higher is better
+            crossover_fitness_buffer.push((parents.len() - winner) as f64);
+        }
+
+        (breeding.crossover)(
+            rng,
+            &crossover_input_buffer,
+            &crossover_fitness_buffer,
+            &mut output_writer,
+            breeding.crossover_settings,
+            &mut crossover_weight_index_buffer,
+        );
+
+        crossover_weight_index_buffer.clear();
+    }
+
+    // mutation
+    output
+        .iter_mut()
+        .skip(parents.len())
+        .for_each(|s| breeding.mutate(rng, s));
+
+    Ok(output)
+}
+
+/// Splits members into Pareto fronts, best front first. A member dominates another one if it is
+/// at least as good in every objective and better in at least one.
+pub fn non_dominated_sort(objectives: &[f64], objective_count: usize) -> Vec<Vec<usize>> {
+    let member_count = objectives.len() / objective_count;
+    let member = |idx: usize| &objectives[idx * objective_count..(idx + 1) * objective_count];
+
+    let dominates = |a: usize, b: usize| {
+        let (a, b) = (member(a), member(b));
+        a.iter().zip(b).all(|(a, b)| a >= b) && a.iter().zip(b).any(|(a, b)| a > b)
+    };
+
+    let mut dominated_by_count = vec![0; member_count];
+    let mut dominating = vec![Vec::new(); member_count];
 
+    for a in 0..member_count {
+        for b in 0..member_count {
+            if dominates(a, b) {
+                dominating[a].push(b);
+            } else if dominates(b, a) {
+                dominated_by_count[a] += 1;
+            }
+        }
+    }
+
+    let mut fronts = Vec::new();
+    let mut current = (0..member_count)
+        .filter(|&idx| dominated_by_count[idx] == 0)
+        .collect::<Vec<_>>();
+
+    while !current.is_empty() {
+        let mut next = Vec::new();
+
+        for &a in &current {
+            for &b in &dominating[a] {
+                dominated_by_count[b] -= 1;
+
+                if dominated_by_count[b] == 0 {
+                    next.push(b);
+                }
+            }
+        }
+
+        fronts.push(current);
+        current = next;
+    }
+
+    fronts
+}
+
+/// Crowding distance of every member of `front`, in the same order. Members at the boundary of
+/// any objective get an infinite distance.
+pub fn crowding_distance(objectives: &[f64], objective_count: usize, front: &[usize]) -> Vec<f64> {
+    let mut distance = vec![0.0; front.len()];
+    let mut by_objective = (0..front.len()).collect::<Vec<_>>();
+
+    for objective in 0..objective_count {
+        let value = |position: usize| objectives[front[position] * objective_count + objective];
+
+        by_objective
+            .sort_unstable_by(|&a, &b| value(a).partial_cmp(&value(b)).unwrap_or(Ordering::Equal));
+
+        let first = by_objective[0];
+        let last = by_objective[front.len() - 1];
+        let range = value(last) - value(first);
+
+        distance[first] = f64::INFINITY;
+        distance[last] = f64::INFINITY;
+
+        if range <= 0.0 {
+            continue;
+        }
+
+        for window in by_objective.windows(3) {
+            distance[window[1]] += (value(window[2]) - value(window[0])) / range;
+        }
+    }
+
+    distance
+}
+
+pub(crate) fn validate_fitness(fitness: &[f64]) -> Result<(), BrainsError> {
+    if fitness.iter().any(|f| !f.is_finite()) {
+        Err(BrainsError::InvalidFitnessValue)
+    } else {
+        Ok(())
+    }
+}
+
+/// Selects parents and fills the remaining space of `output_writer` with their (unmutated)
+/// children
+pub(crate) fn breed<R, S, C, CS, M, MS>(
+    rng: &mut R,
+    population: &[S],
+    fitness: &[f64],
+    breeding: &Breeding<C, CS, M, MS>,
+    output_writer: &mut SpecimenWriter<S>,
+) -> Result<(), BrainsError>
+where
+    R: Rng + ?Sized,
+    C: Fn(&mut R, &[&S], &[f64], &mut SpecimenWriter<S>, &CS, &mut Vec<usize>),
+{
+    let crossover_inputs = breeding.crossover_inputs;
     let mut crossover_input_buffer = Vec::with_capacity(crossover_inputs);
+    let mut crossover_fitness_buffer = Vec::with_capacity(crossover_inputs);
     let mut crossover_weight_index_buffer = Vec::new();
 
-    // TODO: Proper error handling
-    let fitness_alias_table = WeightedIndex::new(Vec::from(fitness)).unwrap();
+    // Number of parents needed to fill the remaining slots, assuming every crossover produces at
+    // least as many children as it consumes parents
+    let parents_needed = {
+        let remaining = output_writer.limit - output_writer.buffer.len();
+        remaining.div_ceil(crossover_inputs) * crossover_inputs
+    };
+
+    let mut selector = Selector::new(
+        rng,
+        breeding.selection_method,
+        breeding.generation,
+        fitness,
+        parents_needed,
+    )?;
 
     while output_writer.can_write() {
         crossover_input_buffer.clear();
+        crossover_fitness_buffer.clear();
 
         // selection
         selection(
             rng,
-            selection_method,
+            &mut selector,
             population,
-            &fitness_alias_table,
+            fitness,
             crossover_inputs,
             &mut crossover_input_buffer,
+            &mut crossover_fitness_buffer,
         );
 
         // crossover
-        crossover(
+        (breeding.crossover)(
             rng,
             &crossover_input_buffer,
-            &mut output_writer,
-            crossover_settings,
+            &crossover_fitness_buffer,
+            output_writer,
+            breeding.crossover_settings,
             &mut crossover_weight_index_buffer,
         );
 
         crossover_weight_index_buffer.clear();
     }
 
-    // mutation
-    output
-        .iter_mut()
-        .skip(elitism)
-        .for_each(|s| mutate(rng, s, mutate_settings));
-
-    output
+    Ok(())
 }
 
 // TODO: Think about if this really belongs into .evolve()
 // TODO: Attempt to re-use this for save_top_n
-fn add_elitism_members<S: Clone>(
+pub(crate) fn add_elitism_members<S: Clone>(
     output_writer: &mut SpecimenWriter<S>,
     population: &[S],
     fitness: &[f64],
@@ -101,18 +459,35 @@ fn add_elitism_members<S: Clone>(
         return;
     }
 
-    // TODO: Think of a more space-efficient implementation
-    let mut elite = population.iter().zip(fitness).collect::<Vec<_>>();
-    elite.sort_unstable_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
-
-    for &(specimen, _) in elite.iter().take(elitism) {
-        output_writer.write(specimen.clone());
+    for idx in rank_by_fitness(fitness).into_iter().take(elitism) {
+        output_writer.write(population[idx].clone());
     }
 }
 
-#[derive(Copy, Clone, Deserialize, Serialize)]
+/// Sample from the standard normal distribution, using the Box-Muller transform
+pub fn standard_normal<R: Rng + ?Sized>(rng: &mut R) -> f64 {
+    // 1 - [0, 1) keeps the logarithm finite
+    let u1 = 1.0 - rng.gen::<f64>();
+    let u2 = rng.gen::<f64>();
+
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+/// Indices of all members, sorted from highest to lowest fitness
+pub(crate) fn rank_by_fitness(fitness: &[f64]) -> Vec<usize> {
+    let mut ranking = (0..fitness.len()).collect::<Vec<_>>();
+    ranking.sort_unstable_by(|&a, &b| {
+        fitness[b]
+            .partial_cmp(&fitness[a])
+            .unwrap_or(Ordering::Equal)
+    });
+    ranking
+}
+
+#[derive(Copy, Clone, Default, Deserialize, Serialize)]
 pub enum SelectionMethod {
     /// Sum fitness, divive by sum, chose t in [0-1], take first element at accumulate sum >= t
+    #[default]
     FitnessProportionate,
 
     /// Equally spaced indices, randomly generated once
@@ -121,31 +496,818 @@ pub enum SelectionMethod {
     /// Randomly chose subset, take strongest
     Tournament(usize),
 
+    /// Randomly chose subset, take strongest with probability p, second strongest with p * (1 - p)
+    /// and so on. Softens the selection pressure of a regular tournament.
+    StochasticTournament {
+        size: usize,
+        winner_probability: f64,
+    },
+
     /// Take only the best X percent
     Truncation(f64),
+
+    /// Probability grows linearly with rank. The best member is `pressure` (in [1, 2]) times as
+    /// likely to be chosen as an average one, the worst `2 - pressure` times.
+    LinearRank { pressure: f64 },
+
+    /// Probability shrinks exponentially with rank. The member at rank r (best is 0) is weighted
+    /// with `base` (in (0, 1)) to the power of r.
+    ExponentialRank { base: f64 },
+
+    /// Softmax over fitness: Members are weighted with exp(f / T), where the temperature T
+    /// depends on the current generation. High temperatures explore, low ones exploit.
+    Boltzmann { temperature: TemperatureSchedule },
+
+    /// Every member is equally likely to be chosen, regardless of fitness
+    Uniform,
+}
+
+/// Per-generation selection state, so that expensive setup (like building an alias table) only
+/// happens once and not for every set of crossover inputs
+enum Selector {
+    Weighted(WeightedIndex<f64>),
+    StochasticUniversal {
+        fitness: Vec<f64>,
+        pointer_count: usize,
+        selected: Vec<usize>,
+    },
+    Tournament {
+        fitness: Vec<f64>,
+        size: usize,
+        winner_probability: f64,
+        contestants: Vec<usize>,
+    },
+    Truncation(Vec<usize>),
+    Uniform(usize),
+}
+
+impl Selector {
+    fn new<R: Rng + ?Sized>(
+        rng: &mut R,
+        method: SelectionMethod,
+        generation: usize,
+        fitness: &[f64],
+        parents_needed: usize,
+    ) -> Result<Selector, BrainsError> {
+        let selector = match method {
+            SelectionMethod::FitnessProportionate => {
+                validate_proportionate_fitness(fitness)?;
+
+                Selector::Weighted(WeightedIndex::new(Vec::from(fitness)).unwrap())
+            }
+            SelectionMethod::StochasticUniversal => {
+                validate_proportionate_fitness(fitness)?;
+
+                let pointer_count = parents_needed.max(1);
+                let mut selected = Vec::with_capacity(pointer_count);
+
+                stochastic_universal_sampling(rng, fitness, pointer_count, &mut selected);
+
+                Selector::StochasticUniversal {
+                    fitness: Vec::from(fitness),
+                    pointer_count,
+                    selected,
+                }
+            }
+            SelectionMethod::Tournament(size) => Selector::Tournament {
+                fitness: Vec::from(fitness),
+                size,
+                winner_probability: 1.0,
+                contestants: Vec::with_capacity(size),
+            },
+            SelectionMethod::StochasticTournament {
+                size,
+                winner_probability,
+            } => Selector::Tournament {
+                fitness: Vec::from(fitness),
+                size,
+                winner_probability,
+                contestants: Vec::with_capacity(size),
+            },
+            SelectionMethod::Truncation(ratio) => {
+                let pool_size = ((ratio * fitness.len() as f64).ceil() as usize).max(1);
+
+                let mut pool = rank_by_fitness(fitness);
+                pool.truncate(pool_size);
+
+                Selector::Truncation(pool)
+            }
+            SelectionMethod::LinearRank { pressure } => {
+                let n = fitness.len() as f64;
+
+                Selector::from_rank_weights(fitness, |rank| {
+                    if fitness.len() == 1 {
+                        1.0
+                    } else {
+                        // Baker's linear ranking, with rank 0 being the best member
+                        let position = n - 1.0 - rank as f64;
+                        (2.0 - pressure) / n + 2.0 * position * (pressure - 1.0) / (n * (n - 1.0))
+                    }
+                })
+            }
+            SelectionMethod::ExponentialRank { base } => {
+                Selector::from_rank_weights(fitness, |rank| base.powi(rank as i32))
+            }
+            SelectionMethod::Boltzmann { temperature } => {
+                let temperature = temperature.temperature(generation);
+
+                // Shifting by the maximum doesn't change the distribution, but keeps exp() from
+                // overflowing
+                let max = fitness.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+                let weights = fitness
+                    .iter()
+                    .map(|f| ((f - max) / temperature).exp())
+                    .collect();
+
+                Selector::Weighted(WeightedIndex::new(weights).unwrap())
+            }
+            SelectionMethod::Uniform => Selector::Uniform(fitness.len()),
+        };
+
+        Ok(selector)
+    }
+
+    fn from_rank_weights<W: Fn(usize) -> f64>(fitness: &[f64], weight: W) -> Selector {
+        let mut weights = vec![0.0; fitness.len()];
+
+        for (rank, idx) in rank_by_fitness(fitness).into_iter().enumerate() {
+            weights[idx] = weight(rank);
+        }
+
+        Selector::Weighted(WeightedIndex::new(weights).unwrap())
+    }
+
+    fn select<R: Rng + ?Sized>(&mut self, rng: &mut R) -> usize {
+        match self {
+            Selector::Weighted(alias_table) => alias_table.sample(rng),
+            Selector::StochasticUniversal {
+                fitness,
+                pointer_count,
+                selected,
+            } => {
+                // Only happens if crossover produces fewer children than it consumes parents
+                if selected.is_empty() {
+                    stochastic_universal_sampling(rng, fitness, *pointer_count, selected);
+                }
+
+                selected.pop().unwrap()
+            }
+            Selector::Tournament {
+                fitness,
+                size,
+                winner_probability,
+                contestants,
+            } => tournament(rng, fitness, *size, *winner_probability, contestants),
+            Selector::Truncation(pool) => *pool.choose(rng).unwrap(),
+            Selector::Uniform(count) => rng.gen_range(0, *count),
+        }
+    }
+}
+
+/// Fitness proportionate methods need non-negative fitness values with a positive sum
+fn validate_proportionate_fitness(fitness: &[f64]) -> Result<(), BrainsError> {
+    if fitness.iter().any(|&f| f < 0.0) {
+        return Err(BrainsError::NegativeFitness);
+    }
+
+    if fitness.iter().all(|&f| f == 0.0) {
+        return Err(BrainsError::AllFitnessZero);
+    }
+
+    Ok(())
+}
+
+/// Places `count` equally spaced pointers over the cumulative fitness, starting at a single random
+/// offset, and writes the index of every member hit by a pointer into `output` in random order.
+fn stochastic_universal_sampling<R: Rng + ?Sized>(
+    rng: &mut R,
+    fitness: &[f64],
+    count: usize,
+    output: &mut Vec<usize>,
+) {
+    let total = fitness.iter().sum::<f64>();
+    let distance = total / count as f64;
+    let start = rng.gen_range(0.0, distance);
+
+    let mut member = 0;
+    let mut cumulative = fitness[0];
+
+    for i in 0..count {
+        let pointer = start + i as f64 * distance;
+
+        while cumulative <= pointer && member < fitness.len() - 1 {
+            member += 1;
+            cumulative += fitness[member];
+        }
+
+        output.push(member);
+    }
+
+    // Pointers are sorted by member, but crossover partners should be random
+    output.shuffle(rng);
+}
+
+/// Samples `size` members with replacement and returns the index of the winner. Only compares
+/// fitness values, so it works for negative and zero fitness as well.
+fn tournament<R: Rng + ?Sized>(
+    rng: &mut R,
+    fitness: &[f64],
+    size: usize,
+    winner_probability: f64,
+    contestants: &mut Vec<usize>,
+) -> usize {
+    contestants.clear();
+    contestants.extend((0..size).map(|_| rng.gen_range(0, fitness.len())));
+
+    if winner_probability >= 1.0 {
+        return *contestants
+            .iter()
+            .max_by(|&&a, &&b| {
+                fitness[a]
+                    .partial_cmp(&fitness[b])
+                    .unwrap_or(Ordering::Equal)
+            })
+            .unwrap();
+    }
+
+    contestants.sort_unstable_by(|&a, &b| {
+        fitness[b]
+            .partial_cmp(&fitness[a])
+            .unwrap_or(Ordering::Equal)
+    });
+
+    for &contestant in contestants.iter() {
+        if rng.gen_range(0.0, 1.0) < winner_probability {
+            return contestant;
+        }
+    }
+
+    *contestants.last().unwrap()
+}
+
+/// How the next round's population is made up. The population size in the config is the
+/// number of parents (mu) for the evolution strategy schemes.
+#[derive(Copy, Clone, Default, Deserialize, Serialize)]
+pub enum GenerationScheme {
+    /// Every member is replaced by offspring, except for the elite
+    #[default]
+    Generational,
+
+    /// Parents survive and compete with their `offspring` children in the next round. Elitism is
+    /// implicit.
+    MuPlusLambda { offspring: usize },
+
+    /// Only the `offspring` children survive. Needs at least as many children as parents.
+    MuCommaLambda { offspring: usize },
 }
 
-impl Default for SelectionMethod {
-    fn default() -> Self {
-        SelectionMethod::FitnessProportionate
+#[derive(Copy, Clone, Default, Deserialize, Serialize)]
+pub enum FitnessShaping {
+    /// Use fitness values as they are
+    #[default]
+    None,
+
+    /// Subtract the lowest fitness, so the worst member ends up at zero. If every member is
+    /// equally fit, they all get 1 instead.
+    ShiftToPositive,
+
+    /// Replace fitness by rank, from 1 for the worst member up to the population size
+    RankTransform,
+
+    /// Subtract the mean and divide by the standard deviation. Produces negative values, so it
+    /// doesn't work with proportionate selection.
+    ZScore,
+
+    /// Rank scaled into [-0.5, 0.5], with the worst member at -0.5. Doesn't work with
+    /// proportionate selection either.
+    CenteredRank,
+}
+
+/// Transforms raw fitness values before they are used for selection. Fails if any of the raw
+/// values is NaN or infinite.
+pub fn shape_fitness(fitness: &[f64], shaping: FitnessShaping) -> Result<Vec<f64>, BrainsError> {
+    validate_fitness(fitness)?;
+
+    let n = fitness.len() as f64;
+
+    let shaped = match shaping {
+        FitnessShaping::None => Vec::from(fitness),
+        FitnessShaping::ShiftToPositive => {
+            let min = fitness.iter().copied().fold(f64::INFINITY, f64::min);
+
+            // Nobody is better than anyone else, but proportionate selection needs a positive sum
+            if fitness.iter().all(|&f| f == min) {
+                vec![1.0; fitness.len()]
+            } else {
+                fitness.iter().map(|f| f - min).collect()
+            }
+        }
+        FitnessShaping::RankTransform => {
+            let mut shaped = vec![0.0; fitness.len()];
+            for (rank, idx) in rank_by_fitness(fitness).into_iter().enumerate() {
+                shaped[idx] = n - rank as f64;
+            }
+            shaped
+        }
+        FitnessShaping::ZScore => {
+            let mean = fitness.iter().sum::<f64>() / n;
+            let std_dev = (fitness.iter().map(|f| (f - mean).powi(2)).sum::<f64>() / n).sqrt();
+
+            if std_dev == 0.0 {
+                vec![0.0; fitness.len()]
+            } else {
+                fitness.iter().map(|f| (f - mean) / std_dev).collect()
+            }
+        }
+        FitnessShaping::CenteredRank => {
+            let mut shaped = vec![0.0; fitness.len()];
+            if fitness.len() > 1 {
+                for (rank, idx) in rank_by_fitness(fitness).into_iter().enumerate() {
+                    shaped[idx] = (n - 1.0 - rank as f64) / (n - 1.0) - 0.5;
+                }
+            }
+            shaped
+        }
+    };
+
+    Ok(shaped)
+}
+
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub enum TemperatureSchedule {
+    Constant(f64),
+
+    /// Goes linearly from `start` to `end` within `generations` generations, then stays at `end`
+    LinearDecay {
+        start: f64,
+        end: f64,
+        generations: usize,
+    },
+
+    /// Multiplied by `rate` each generation, but never drops below `min`
+    ExponentialDecay {
+        start: f64,
+        rate: f64,
+        min: f64,
+    },
+}
+
+impl TemperatureSchedule {
+    pub fn temperature(&self, generation: usize) -> f64 {
+        match *self {
+            TemperatureSchedule::Constant(t) => t,
+            TemperatureSchedule::LinearDecay {
+                start,
+                end,
+                generations,
+            } => {
+                if generation >= generations {
+                    end
+                } else {
+                    start + (end - start) * generation as f64 / generations as f64
+                }
+            }
+            TemperatureSchedule::ExponentialDecay { start, rate, min } => {
+                (start * rate.powf(generation as f64)).max(min)
+            }
+        }
     }
 }
 
 fn selection<'a, R: Rng + ?Sized, S>(
     rng: &mut R,
-    method: SelectionMethod,
+    selector: &mut Selector,
     population: &'a [S],
-    fitness_alias_table: &WeightedIndex<f64>,
+    fitness: &[f64],
     count: usize,
     output: &mut Vec<&'a S>,
+    output_fitness: &mut Vec<f64>,
 ) {
-    match method {
-        SelectionMethod::FitnessProportionate => {
-            for _ in 0..count {
-                let candidate_idx = fitness_alias_table.sample(rng);
-                output.push(&population[candidate_idx]);
+    for _ in 0..count {
+        let candidate_idx = selector.select(rng);
+        output.push(&population[candidate_idx]);
+        output_fitness.push(fitness[candidate_idx]);
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+
+    /// Breeding on plain numbers: Crossover copies the parents and mutation adds 10, so children
+    /// can be told apart from their parents
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn marking_breeding(
+        selection_method: SelectionMethod,
+        elitism: usize,
+    ) -> Breeding<
+        'static,
+        impl Fn(&mut StdRng, &[&f64], &[f64], &mut SpecimenWriter<f64>, &(), &mut Vec<usize>),
+        (),
+        impl Fn(&mut StdRng, &mut f64, &()),
+        (),
+    > {
+        Breeding {
+            selection_method,
+            generation: 0,
+            elitism,
+            crossover_inputs: 2,
+            crossover: |_: &mut StdRng,
+                        input: &[&f64],
+                        _: &[f64],
+                        output: &mut SpecimenWriter<f64>,
+                        _: &(),
+                        _: &mut Vec<usize>| {
+                input.iter().for_each(|&&s| output.write(s))
+            },
+            crossover_settings: &(),
+            mutate: |_: &mut StdRng, s: &mut f64, _: &()| *s += 10.0,
+            mutate_settings: &(),
+        }
+    }
+
+    fn sorted(mut indices: Vec<usize>) -> Vec<usize> {
+        indices.sort_unstable();
+        indices
+    }
+
+    #[test]
+    fn non_dominated_sort_splits_into_fronts() {
+        #[rustfmt::skip]
+        let objectives = [
+            1.0, 5.0,
+            2.0, 4.0,
+            3.0, 3.0,
+            1.0, 1.0,
+            2.0, 2.0,
+            0.0, 0.0,
+            2.0, 2.0,
+        ];
+
+        let fronts = non_dominated_sort(&objectives, 2)
+            .into_iter()
+            .map(sorted)
+            .collect::<Vec<_>>();
+
+        // Equal members don't dominate each other
+        assert_eq!(fronts, vec![vec![0, 1, 2], vec![4, 6], vec![3], vec![5]]);
+    }
+
+    #[test]
+    fn crowding_distance_favours_boundaries_and_sparse_regions() {
+        #[rustfmt::skip]
+        let objectives = [
+            0.0, 4.0,
+            1.0, 3.0,
+            3.0, 1.0,
+            4.0, 0.0,
+            3.5, 0.5,
+        ];
+
+        let distance = crowding_distance(&objectives, 2, &[0, 1, 2, 3, 4]);
+
+        assert!(distance[0].is_infinite() && distance[3].is_infinite());
+        assert!((distance[1] - 1.5).abs() < 1e-12);
+        assert!((distance[2] - 1.25).abs() < 1e-12);
+        assert!((distance[4] - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn nsga2_keeps_best_of_parents_and_offspring() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let population = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0];
+        #[rustfmt::skip]
+        let objectives = [
+            1.0, 5.0,
+            2.0, 4.0,
+            3.0, 3.0,
+            1.0, 1.0,
+            2.0, 2.0,
+            0.0, 0.0,
+        ];
+
+        let breeding = marking_breeding(SelectionMethod::default(), 0);
+        let next_gen = evolve_nsga2(&mut rng, &population, &objectives, 2, 2, &breeding).unwrap();
+
+        // The first front is cut by crowding distance, which keeps its boundary members
+        assert_eq!(next_gen.len(), 4);
+        assert_eq!(&next_gen[..2], &[0.0, 2.0]);
+        assert!(next_gen[2..].iter().all(|&s| s == 10.0 || s == 12.0));
+    }
+
+    #[test]
+    fn shift_to_positive_keeps_differences() {
+        let shaped = shape_fitness(&[-2.0, 1.0, 0.5], FitnessShaping::ShiftToPositive).unwrap();
+        assert_eq!(shaped, vec![0.0, 3.0, 2.5]);
+
+        // Equal fitness must still be selectable proportionately
+        let shaped = shape_fitness(&[-4.0, -4.0, -4.0], FitnessShaping::ShiftToPositive).unwrap();
+        assert_eq!(shaped, vec![1.0, 1.0, 1.0]);
+        assert!(validate_proportionate_fitness(&shaped).is_ok());
+    }
+
+    #[test]
+    fn rank_transforms_follow_fitness_order() {
+        let fitness = [0.3, -7.0, 12.0, 0.1];
+
+        let shaped = shape_fitness(&fitness, FitnessShaping::RankTransform).unwrap();
+        assert_eq!(shaped, vec![3.0, 1.0, 4.0, 2.0]);
+
+        let shaped = shape_fitness(&fitness, FitnessShaping::CenteredRank).unwrap();
+        let expected = [1.0 / 6.0, -0.5, 0.5, -1.0 / 6.0];
+        assert!(shaped
+            .iter()
+            .zip(&expected)
+            .all(|(s, e)| (s - e).abs() < 1e-12));
+    }
+
+    #[test]
+    fn z_score_has_zero_mean_and_unit_deviation() {
+        let shaped = shape_fitness(&[1.0, 2.0, 3.0, 10.0], FitnessShaping::ZScore).unwrap();
+
+        let mean = shaped.iter().sum::<f64>() / 4.0;
+        let variance = shaped.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / 4.0;
+
+        assert!(mean.abs() < 1e-12);
+        assert!((variance - 1.0).abs() < 1e-12);
+        assert!(shaped[3] > shaped[2] && shaped[2] > shaped[1] && shaped[1] > shaped[0]);
+
+        let shaped = shape_fitness(&[5.0, 5.0], FitnessShaping::ZScore).unwrap();
+        assert_eq!(shaped, vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn shaping_rejects_invalid_fitness() {
+        assert!(shape_fitness(&[1.0, f64::NAN], FitnessShaping::None).is_err());
+        assert!(shape_fitness(&[f64::INFINITY], FitnessShaping::RankTransform).is_err());
+    }
+
+    #[test]
+    fn stochastic_universal_counts_match_fitness_proportions() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let fitness = [1.0, 2.0, 3.0, 0.0, 4.0, 0.5, 1.5];
+        let total = fitness.iter().sum::<f64>();
+
+        for &count in &[7, 10, 20, 100] {
+            for _ in 0..100 {
+                let mut selected = Vec::new();
+                stochastic_universal_sampling(&mut rng, &fitness, count, &mut selected);
+
+                assert_eq!(selected.len(), count);
+
+                for (idx, f) in fitness.iter().enumerate() {
+                    let expected = f / total * count as f64;
+                    let actual = selected.iter().filter(|&&s| s == idx).count() as f64;
+
+                    // SUS guarantees every member is selected either floor or ceil of its expected
+                    // number of times
+                    assert!(actual >= expected.floor() && actual <= expected.ceil());
+                }
             }
         }
-        _ => unimplemented!(),
+    }
+
+    #[test]
+    fn stochastic_universal_selector_refills_pointers() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let fitness = [0.0, 1.0, 0.0, 3.0];
+
+        let mut selector = Selector::new(
+            &mut rng,
+            SelectionMethod::StochasticUniversal,
+            0,
+            &fitness,
+            4,
+        )
+        .unwrap();
+
+        let mut counts = [0; 4];
+        for _ in 0..400 {
+            counts[selector.select(&mut rng)] += 1;
+        }
+
+        assert_eq!(counts, [0, 100, 0, 300]);
+    }
+
+    #[test]
+    fn tournament_handles_negative_and_zero_fitness() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let fitness = [-3.0, 0.0, -1.0, -2.0];
+
+        let mut selector =
+            Selector::new(&mut rng, SelectionMethod::Tournament(4), 0, &fitness, 0).unwrap();
+
+        let mut counts = [0; 4];
+        for _ in 0..1000 {
+            counts[selector.select(&mut rng)] += 1;
+        }
+
+        // The fittest member wins every tournament it takes part in, the weakest only wins if it
+        // is the sole contestant
+        assert!(counts[1] > counts[2] && counts[2] > counts[3] && counts[3] > counts[0]);
+    }
+
+    #[test]
+    fn stochastic_tournament_lets_weaker_members_win() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let fitness = [1.0, 2.0];
+
+        let mut selector = Selector::new(
+            &mut rng,
+            SelectionMethod::StochasticTournament {
+                size: 2,
+                winner_probability: 0.75,
+            },
+            0,
+            &fitness,
+            0,
+        )
+        .unwrap();
+
+        let mut counts = [0; 2];
+        for _ in 0..1000 {
+            counts[selector.select(&mut rng)] += 1;
+        }
+
+        assert!(counts[0] > 0 && counts[1] > counts[0]);
+    }
+
+    /// How often every member is chosen in `draws` selections
+    fn selection_counts(
+        method: SelectionMethod,
+        generation: usize,
+        fitness: &[f64],
+        draws: usize,
+    ) -> Vec<usize> {
+        let mut rng = StdRng::seed_from_u64(4);
+        let mut selector = Selector::new(&mut rng, method, generation, fitness, 0).unwrap();
+
+        let mut counts = vec![0; fitness.len()];
+        for _ in 0..draws {
+            counts[selector.select(&mut rng)] += 1;
+        }
+
+        counts
+    }
+
+    #[test]
+    fn truncation_selects_only_the_best() {
+        let fitness = [-1.0, 5.0, 3.0, 2.0, 4.0];
+
+        let counts = selection_counts(SelectionMethod::Truncation(0.4), 0, &fitness, 1000);
+
+        assert_eq!(counts[0] + counts[2] + counts[3], 0);
+        assert!(counts[1] > 400 && counts[4] > 400);
+
+        // The pool is rounded up and always holds at least one member
+        let counts = selection_counts(SelectionMethod::Truncation(0.5), 0, &fitness, 1000);
+        assert_eq!(counts.iter().filter(|&&c| c > 0).count(), 3);
+        assert!(counts[2] > 0);
+
+        let counts = selection_counts(SelectionMethod::Truncation(0.01), 0, &fitness, 100);
+        assert_eq!(counts, vec![0, 100, 0, 0, 0]);
+    }
+
+    /// Whether every count is within `tolerance` of the expected one
+    fn counts_near(counts: &[usize], expected: &[usize], tolerance: usize) -> bool {
+        counts
+            .iter()
+            .zip(expected)
+            .all(|(&c, &e)| (c as i64 - e as i64).abs() <= tolerance as i64)
+    }
+
+    #[test]
+    fn linear_rank_follows_pressure() {
+        let fitness = [3.0, -1.0, 7.0, 0.0];
+
+        // Weights of 3/6, 2/6, 1/6 and 0 from best to worst
+        let counts = selection_counts(
+            SelectionMethod::LinearRank { pressure: 2.0 },
+            0,
+            &fitness,
+            6000,
+        );
+        assert_eq!(counts[1], 0);
+        assert!(counts_near(&counts, &[2000, 0, 3000, 1000], 150));
+
+        let counts = selection_counts(
+            SelectionMethod::LinearRank { pressure: 1.0 },
+            0,
+            &fitness,
+            6000,
+        );
+        assert!(counts_near(&counts, &[1500; 4], 150));
+
+        let counts = selection_counts(SelectionMethod::LinearRank { pressure: 1.5 }, 0, &[2.0], 10);
+        assert_eq!(counts, vec![10]);
+    }
+
+    #[test]
+    fn exponential_rank_halves_with_every_rank() {
+        let fitness = [3.0, -1.0, 7.0, 0.0];
+
+        let counts = selection_counts(
+            SelectionMethod::ExponentialRank { base: 0.5 },
+            0,
+            &fitness,
+            7500,
+        );
+
+        assert!(counts_near(&counts, &[2000, 500, 4000, 1000], 150));
+    }
+
+    #[test]
+    fn temperature_schedules_decay_to_their_floor() {
+        let linear = TemperatureSchedule::LinearDecay {
+            start: 10.0,
+            end: 1.0,
+            generations: 9,
+        };
+        let exponential = TemperatureSchedule::ExponentialDecay {
+            start: 8.0,
+            rate: 0.5,
+            min: 1.0,
+        };
+
+        assert_eq!(TemperatureSchedule::Constant(2.0).temperature(100), 2.0);
+
+        let temperatures = [0, 3, 9, 20].iter().map(|&g| linear.temperature(g));
+        assert_eq!(temperatures.collect::<Vec<_>>(), vec![10.0, 7.0, 1.0, 1.0]);
+
+        let temperatures = [0, 2, 3, 5].iter().map(|&g| exponential.temperature(g));
+        assert_eq!(temperatures.collect::<Vec<_>>(), vec![8.0, 2.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn boltzmann_sharpens_as_temperature_drops() {
+        let fitness = [0.0, 1.0, -2.0];
+        let temperature = TemperatureSchedule::ExponentialDecay {
+            start: 1000.0,
+            rate: 0.1,
+            min: 0.01,
+        };
+
+        // Nearly uniform while hot
+        let counts = selection_counts(
+            SelectionMethod::Boltzmann { temperature },
+            0,
+            &fitness,
+            3000,
+        );
+        assert!(counts_near(&counts, &[1000; 3], 100));
+
+        // exp(1 / T) against exp(0) and exp(-2 / T)
+        let counts = selection_counts(
+            SelectionMethod::Boltzmann { temperature },
+            3,
+            &fitness,
+            3000,
+        );
+        let expected = [1.0, 1.0f64.exp(), (-2.0f64).exp()]
+            .iter()
+            .map(|w| (3000.0 * w / (1.0 + 1.0f64.exp() + (-2.0f64).exp())) as usize)
+            .collect::<Vec<_>>();
+        assert!(counts_near(&counts, &expected, 100));
+
+        // Greedy once cold, even with large fitness values that would overflow exp()
+        let counts = selection_counts(
+            SelectionMethod::Boltzmann { temperature },
+            10,
+            &fitness,
+            100,
+        );
+        assert_eq!(counts, vec![0, 100, 0]);
+
+        let counts = selection_counts(
+            SelectionMethod::Boltzmann { temperature },
+            10,
+            &[1e6, 2e6],
+            100,
+        );
+        assert_eq!(counts, vec![0, 100]);
+    }
+
+    #[test]
+    fn mu_lambda_breeds_from_the_best() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let population = [1.0, 6.0, 3.0, 5.0, 2.0, 4.0];
+        let breeding = marking_breeding(SelectionMethod::FitnessProportionate, 0);
+
+        // mu + lambda: The parents stay in front of their children
+        let next =
+            evolve_mu_lambda(&mut rng, &population, &population, 2, 4, true, &breeding).unwrap();
+
+        assert_eq!(next.len(), 6);
+        assert_eq!(next[..2], [6.0, 5.0]);
+        assert!(next[2..].iter().all(|c| *c == 16.0 || *c == 15.0));
+
+        // mu, lambda: Only the children remain
+        let next =
+            evolve_mu_lambda(&mut rng, &population, &population, 2, 4, false, &breeding).unwrap();
+
+        assert_eq!(next.len(), 4);
+        assert!(next.iter().all(|c| *c == 16.0 || *c == 15.0));
     }
 }
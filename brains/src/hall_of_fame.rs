@@ -0,0 +1,149 @@
+use crate::error::BrainsError;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct HallOfFameSettings {
+    pub size: usize,
+    /// Adds the hall of fame to the breeding pool of every generation
+    pub reinject: bool,
+}
+
+impl Default for HallOfFameSettings {
+    fn default() -> Self {
+        HallOfFameSettings {
+            size: 10,
+            reinject: false,
+        }
+    }
+}
+
+impl HallOfFameSettings {
+    pub fn validate(&self) -> Result<(), BrainsError> {
+        if self.size == 0 {
+            return Err(BrainsError::HallOfFameSizeZero);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct HallOfFameEntry<S> {
+    pub member: S,
+    pub fitness: f64,
+    /// Generation in which the member first made it into the hall of fame
+    pub generation: usize,
+    /// Identity of the member, computed again after loading
+    #[serde(skip)]
+    key: Option<u64>,
+}
+
+/// The best members ever seen, across all generations, sorted from best to worst
+#[derive(Deserialize, Serialize, Clone)]
+pub struct HallOfFame<S> {
+    entries: Vec<HallOfFameEntry<S>>,
+}
+
+impl<S> Default for HallOfFame<S> {
+    fn default() -> Self {
+        HallOfFame {
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<S: Clone> HallOfFame<S> {
+    pub fn entries(&self) -> &[HallOfFameEntry<S>] {
+        &self.entries[..]
+    }
+
+    /// Records every member that beats the weakest entry, or every member while there is still
+    /// room. Members that are already in the hall of fame (as decided by equal `key`s) only have
+    /// their fitness raised if they did better this time.
+    pub fn update<K: Fn(&S) -> u64>(
+        &mut self,
+        population: &[S],
+        fitness: &[f64],
+        generation: usize,
+        size: usize,
+        key: K,
+    ) {
+        assert_eq!(population.len(), fitness.len());
+
+        for entry in &mut self.entries {
+            if entry.key.is_none() {
+                entry.key = Some(key(&entry.member));
+            }
+        }
+
+        for (specimen, &f) in population.iter().zip(fitness) {
+            if !f.is_finite() {
+                continue;
+            }
+
+            let specimen_key = key(specimen);
+
+            if let Some(entry) = self
+                .entries
+                .iter_mut()
+                .find(|e| e.key == Some(specimen_key))
+            {
+                entry.fitness = entry.fitness.max(f);
+            } else {
+                let is_full = self.entries.len() >= size;
+
+                if is_full && self.entries.last().map(|e| f <= e.fitness).unwrap_or(false) {
+                    continue;
+                }
+
+                self.entries.push(HallOfFameEntry {
+                    member: specimen.clone(),
+                    fitness: f,
+                    generation,
+                    key: Some(specimen_key),
+                });
+            }
+
+            self.entries
+                .sort_by(|a, b| b.fitness.partial_cmp(&a.fitness).unwrap_or(Ordering::Equal));
+            self.entries.truncate(size);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(specimen: &f64) -> u64 {
+        specimen.to_bits()
+    }
+
+    #[test]
+    fn keeps_best_members_across_generations() {
+        let mut hall_of_fame = HallOfFame::default();
+
+        hall_of_fame.update(&[1.0, 2.0, 3.0], &[1.0, 2.0, 3.0], 0, 2, key);
+        hall_of_fame.update(&[4.0, 0.5], &[2.5, 0.5], 1, 2, key);
+
+        let entries = hall_of_fame.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].member, entries[0].generation), (3.0, 0));
+        assert_eq!((entries[1].member, entries[1].generation), (4.0, 1));
+    }
+
+    #[test]
+    fn known_members_only_improve_their_fitness() {
+        let mut hall_of_fame = HallOfFame::default();
+
+        hall_of_fame.update(&[1.0, 2.0], &[5.0, 1.0], 0, 3, key);
+        hall_of_fame.update(&[1.0, 2.0, 2.0], &[3.0, 4.0, f64::NAN], 1, 3, key);
+
+        let entries = hall_of_fame.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].member, entries[0].fitness), (1.0, 5.0));
+        assert_eq!((entries[1].member, entries[1].fitness), (2.0, 4.0));
+        assert_eq!(entries[1].generation, 0);
+    }
+}
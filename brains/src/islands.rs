@@ -0,0 +1,269 @@
+use crate::{
+    error::BrainsError,
+    gen::{self, Breeding, SpecimenWriter},
+};
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct IslandSettings {
+    pub count: usize,
+    /// Migration happens every this many generations
+    pub migration_interval: usize,
+    /// Number of best members that every island sends away per migration
+    pub migrants: usize,
+    pub topology: MigrationTopology,
+}
+
+impl Default for IslandSettings {
+    fn default() -> Self {
+        IslandSettings {
+            count: 4,
+            migration_interval: 10,
+            migrants: 2,
+            topology: MigrationTopology::Ring,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy)]
+pub enum MigrationTopology {
+    /// Island i sends its migrants to island i + 1, the last one to the first one
+    Ring,
+
+    /// Every island sends its migrants to a different, randomly chosen island
+    Random,
+}
+
+impl IslandSettings {
+    pub fn validate(&self, population_size: usize) -> Result<(), BrainsError> {
+        if self.count == 0 || self.count > population_size {
+            return Err(BrainsError::IslandCountInvalid);
+        }
+
+        if self.migration_interval == 0 {
+            return Err(BrainsError::IslandMigrationIntervalZero);
+        }
+
+        if self.migrants >= population_size / self.count {
+            return Err(BrainsError::IslandMigrantsInvalid);
+        }
+
+        Ok(())
+    }
+}
+
+/// Splits `member_count` members into `island_count` contiguous, (almost) equally sized islands
+pub fn assign_islands(member_count: usize, island_count: usize) -> Vec<usize> {
+    (0..member_count)
+        .map(|idx| idx * island_count / member_count)
+        .collect()
+}
+
+/// Evolves every island on its own with `gen::evolve`. Every `migration_interval` generations,
+/// each island first sends copies of its best members to another island, where they replace the
+/// weakest ones. The elite of every island is its share of `breeding.elitism`. The returned
+/// population is ordered by island and `islands` is updated to match.
+pub fn evolve_islands<R, S, C, CS, M, MS>(
+    rng: &mut R,
+    population: &[S],
+    fitness: &[f64],
+    islands: &mut Vec<usize>,
+    settings: &IslandSettings,
+    breeding: &Breeding<C, CS, M, MS>,
+) -> Result<Vec<S>, BrainsError>
+where
+    S: Clone,
+    R: Rng + ?Sized,
+    C: Fn(&mut R, &[&S], &[f64], &mut SpecimenWriter<S>, &CS, &mut Vec<usize>),
+    M: Fn(&mut R, &mut S, &MS),
+{
+    assert_eq!(population.len(), fitness.len());
+
+    gen::validate_fitness(fitness)?;
+
+    let generation = breeding.generation;
+
+    let assigned;
+    let member_islands = if islands.len() == population.len() {
+        &islands[..]
+    } else {
+        assigned = assign_islands(population.len(), settings.count);
+        &assigned[..]
+    };
+
+    let mut island_members = vec![Vec::new(); settings.count];
+    let mut island_fitness = vec![Vec::new(); settings.count];
+
+    for ((specimen, &f), &island) in population.iter().zip(fitness).zip(member_islands) {
+        island_members[island].push(specimen.clone());
+        island_fitness[island].push(f);
+    }
+
+    if settings.count > 1 && generation > 0 && generation % settings.migration_interval == 0 {
+        migrate(
+            rng,
+            &mut island_members,
+            &mut island_fitness,
+            settings.migrants,
+            settings.topology,
+        );
+    }
+
+    // Only replaces `islands` once every island evolved successfully
+    let mut output = Vec::with_capacity(population.len());
+    let mut next_islands = Vec::with_capacity(population.len());
+
+    for (island, (members, fitness)) in island_members.iter().zip(&island_fitness).enumerate() {
+        if members.is_empty() {
+            continue;
+        }
+
+        let next_gen = gen::evolve(
+            rng,
+            members,
+            fitness,
+            &Breeding {
+                elitism: breeding.elitism * members.len() / population.len(),
+                ..breeding.by_ref()
+            },
+        )?;
+
+        next_islands.extend(next_gen.iter().map(|_| island));
+        output.extend(next_gen);
+    }
+
+    *islands = next_islands;
+
+    Ok(output)
+}
+
+fn migrate<R: Rng + ?Sized, S: Clone>(
+    rng: &mut R,
+    island_members: &mut [Vec<S>],
+    island_fitness: &mut [Vec<f64>],
+    migrants: usize,
+    topology: MigrationTopology,
+) {
+    let island_count = island_members.len();
+
+    // Emigrants are picked before anyone arrives, so migrants never travel twice
+    let emigrants = island_members
+        .iter()
+        .zip(island_fitness.iter())
+        .map(|(members, fitness)| {
+            gen::rank_by_fitness(fitness)
+                .into_iter()
+                .take(migrants)
+                .map(|idx| (members[idx].clone(), fitness[idx]))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    for (source, emigrants) in emigrants.into_iter().enumerate() {
+        let target = match topology {
+            MigrationTopology::Ring => (source + 1) % island_count,
+            MigrationTopology::Random => {
+                let target = rng.gen_range(0, island_count - 1);
+                if target >= source {
+                    target + 1
+                } else {
+                    target
+                }
+            }
+        };
+
+        let weakest = gen::rank_by_fitness(&island_fitness[target])
+            .into_iter()
+            .rev()
+            .take(emigrants.len())
+            .collect::<Vec<_>>();
+
+        for (idx, (specimen, f)) in weakest.into_iter().zip(emigrants) {
+            island_members[target][idx] = specimen;
+            island_fitness[target][idx] = f;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen::{tests::marking_breeding, SelectionMethod};
+    use rand::rngs::StdRng;
+
+    fn settings() -> IslandSettings {
+        IslandSettings {
+            count: 2,
+            migration_interval: 10,
+            migrants: 1,
+            topology: MigrationTopology::Ring,
+        }
+    }
+
+    #[test]
+    fn ring_migration_replaces_weakest_with_neighbours_best() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut members = vec![vec![1.0, 2.0], vec![4.0, 3.0], vec![5.0, 6.0]];
+        let mut fitness = members.clone();
+
+        migrate(
+            &mut rng,
+            &mut members,
+            &mut fitness,
+            1,
+            MigrationTopology::Ring,
+        );
+
+        // Emigrants are picked before anyone arrives, so 6 doesn't travel on to island 1
+        assert_eq!(
+            members,
+            vec![vec![6.0, 2.0], vec![4.0, 2.0], vec![4.0, 6.0]]
+        );
+        assert_eq!(fitness, members);
+    }
+
+    #[test]
+    fn output_is_ordered_by_island() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let population = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0];
+        let mut islands = vec![1, 0, 1, 0, 1, 0];
+
+        let next_gen = evolve_islands(
+            &mut rng,
+            &population,
+            &population,
+            &mut islands,
+            &settings(),
+            &marking_breeding(SelectionMethod::Tournament(2), 2),
+        )
+        .unwrap();
+
+        assert_eq!(islands, vec![0, 0, 0, 1, 1, 1]);
+
+        // Every island keeps its own best member
+        assert_eq!(next_gen[0], 5.0);
+        assert_eq!(next_gen[3], 4.0);
+        assert!([1.0, 3.0, 5.0].iter().any(|&p| next_gen[1] == p + 10.0));
+        assert!([0.0, 2.0, 4.0].iter().any(|&p| next_gen[4] == p + 10.0));
+    }
+
+    #[test]
+    fn islands_are_kept_on_error() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let population = [0.0, 1.0, 2.0, 3.0];
+        let mut islands = vec![1, 0, 1, 0];
+
+        let result = evolve_islands(
+            &mut rng,
+            &population,
+            &[0.0; 4],
+            &mut islands,
+            &settings(),
+            &marking_breeding(SelectionMethod::FitnessProportionate, 0),
+        );
+
+        assert!(matches!(result, Err(BrainsError::AllFitnessZero)));
+        assert_eq!(islands, vec![1, 0, 1, 0]);
+    }
+}
@@ -1,14 +1,26 @@
+pub mod adaptive_mutation;
+pub mod alps;
+pub mod cma_es;
 pub mod config;
+pub mod differential_evolution;
 pub mod error;
 pub mod gen;
+pub mod hall_of_fame;
+pub mod islands;
+pub mod map_elites;
 pub mod nn;
+pub mod novelty;
+pub mod openai_es;
+pub mod speciation;
 
 use config::{Config, ConfigTemplate};
 use error::BrainsError;
+use gen::GenerationScheme;
 use libc::{c_char, c_double};
 use rand::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::{
+    borrow::Cow,
     cmp::Ordering,
     ffi::{CStr, CString},
     fmt::Debug,
@@ -23,6 +35,52 @@ pub struct Population {
     #[serde(skip)]
     config: Option<Config>,
     generation: usize,
+    /// Members replaced by steady state evolution since the generation counter last went up
+    #[serde(default)]
+    steady_state_replacements: usize,
+    #[serde(default)]
+    species: Option<speciation::Species<nn::NeuralNetwork>>,
+    /// Island of every member, empty if the island model isn't used
+    #[serde(default)]
+    islands: Vec<usize>,
+    #[serde(default)]
+    age_layers: alps::AgeLayers,
+    #[serde(default)]
+    novelty_archive: novelty::NoveltyArchive,
+    #[serde(default)]
+    map_elites: Option<map_elites::MapElitesArchive<nn::NeuralNetwork>>,
+    #[serde(default)]
+    hall_of_fame: hall_of_fame::HallOfFame<nn::NeuralNetwork>,
+    #[serde(default)]
+    mutation_controller: adaptive_mutation::MutationController,
+    #[serde(default)]
+    cma_es: Option<cma_es::CmaEs>,
+    #[serde(default)]
+    openai_es: Option<openai_es::OpenAiEs>,
+    #[serde(default)]
+    differential_evolution: Option<differential_evolution::DifferentialEvolution>,
+}
+
+impl Population {
+    /// Population at generation 0, without any of the state that is kept between generations
+    fn new(members: Vec<nn::NeuralNetwork>, config: Option<Config>) -> Population {
+        Population {
+            members,
+            config,
+            generation: 0,
+            steady_state_replacements: 0,
+            species: None,
+            islands: Vec::new(),
+            age_layers: Default::default(),
+            novelty_archive: Default::default(),
+            map_elites: None,
+            hall_of_fame: Default::default(),
+            mutation_controller: Default::default(),
+            cma_es: None,
+            openai_es: None,
+            differential_evolution: None,
+        }
+    }
 }
 
 static mut LAST_ERROR: Option<CString> = None;
@@ -96,33 +154,99 @@ pub unsafe extern "C" fn build_population_from_config(
         Err(e) => return with_last_error(e),
     };
 
-    let mut members = vec![config.network().clone(); config_template.population_size];
-
-    // Shuffle network weight according to config
     let mut rng = thread_rng();
 
-    for nn in &mut members {
-        for nn_layer in nn.layers_mut() {
-            for nn_weight in nn_layer.all_weights_mut() {
-                *nn_weight = rng.gen_range(config_template.min_weight, config_template.max_weight);
-            }
+    let mut members = (0..config_template.population_size)
+        .map(|_| random_network(&mut rng, &config))
+        .collect::<Vec<_>>();
+
+    let species = config.speciation().map(|settings| {
+        let mut species = speciation::Species::new(settings);
+        species.assign(
+            &mut rng,
+            &members,
+            nn::NeuralNetwork::compatibility_distance,
+            settings,
+        );
+        species
+    });
+
+    let cma_es = config.cma_es().map(|settings| {
+        let state = cma_es::CmaEs::new(members[0].flattened_weights(), settings);
+        let samples = state.sample(&mut rng, members.len());
+
+        for (nn, weights) in members.iter_mut().zip(samples) {
+            nn.set_flattened_weights(&weights);
         }
-    }
+
+        state
+    });
+
+    let openai_es = config.openai_es().map(|settings| {
+        let state = openai_es::OpenAiEs::new(members[0].flattened_weights());
+        let samples = state.sample(&mut rng, members.len(), settings);
+
+        for (nn, weights) in members.iter_mut().zip(samples) {
+            nn.set_flattened_weights(&weights);
+        }
+
+        state
+    });
+
+    // The first members become the targets once they are evaluated
+    let differential_evolution = config.differential_evolution().map(|_| Default::default());
+
+    let islands = config
+        .islands()
+        .map(|settings| islands::assign_islands(members.len(), settings.count))
+        .unwrap_or_default();
+
+    let age_layers = config
+        .alps()
+        .map(|settings| alps::AgeLayers::new(members.len(), settings))
+        .unwrap_or_default();
 
     *count = members.len();
     *inputs = members[0].input_count();
     *outputs = members[0].output_count();
 
     let population_box = Box::new(Population {
-        members,
-        config: Some(config),
-        generation: 0,
+        species,
+        islands,
+        age_layers,
+        cma_es,
+        openai_es,
+        differential_evolution,
+        ..Population::new(members, Some(config))
     });
     *population = Box::into_raw(population_box);
 
     BrainsError::None
 }
 
+/// A copy of the configured network with random weights, the way the initial population is built
+fn random_network<R: Rng + ?Sized>(rng: &mut R, config: &Config) -> nn::NeuralNetwork {
+    let mut nn = config.network().clone();
+
+    for nn_layer in nn.layers_mut() {
+        for nn_weight in nn_layer.all_weights_mut() {
+            *nn_weight = rng.gen_range(config.template().min_weight, config.template().max_weight);
+        }
+    }
+
+    if let Some(self_adaptation) = config.mutation_settings().self_adaptation() {
+        let sigma_count = if self_adaptation.per_layer {
+            nn.layers().len()
+        } else {
+            1
+        };
+
+        *nn.mutation_sigmas_mut() = vec![self_adaptation.initial_sigma; sigma_count];
+    }
+
+    nn
+}
+
 // TODO: Change this embarrasing function name
 #[no_mangle]
 pub unsafe extern "C" fn load_existing_population(
@@ -205,6 +329,94 @@ pub unsafe extern "C" fn load_existing_population(
         return with_last_error(BrainsError::PopulationConfigMismatch);
     }
 
+    if let Some(settings) = config.as_ref().and_then(|c| c.speciation()) {
+        let species = population
+            .species
+            .get_or_insert_with(|| speciation::Species::new(settings));
+
+        if !species.is_assigned_to(&population.members) {
+            species.assign(
+                &mut thread_rng(),
+                &population.members,
+                nn::NeuralNetwork::compatibility_distance,
+                settings,
+            );
+        }
+    }
+
+    if let Some(settings) = config.as_ref().and_then(|c| c.islands()) {
+        let valid_assignment = population.islands.len() == population.members.len()
+            && population.islands.iter().all(|&i| i < settings.count);
+
+        if !valid_assignment {
+            population.islands = islands::assign_islands(population.members.len(), settings.count);
+        }
+    }
+
+    if let Some(settings) = config.as_ref().and_then(|c| c.alps()) {
+        if !population
+            .age_layers
+            .is_assigned_to(population.members.len(), settings)
+        {
+            population.age_layers = alps::AgeLayers::new(population.members.len(), settings);
+        }
+    }
+
+    if let (Some(archive), Some(settings)) = (
+        &population.map_elites,
+        config.as_ref().and_then(|c| c.map_elites()),
+    ) {
+        if !archive.matches(settings) {
+            return with_last_error(BrainsError::PopulationConfigMismatch);
+        }
+    }
+
+    if let Some(settings) = config.as_ref().and_then(|c| c.cma_es()) {
+        if population.members.len() < 2 {
+            return with_last_error(BrainsError::CmaEsPopulationTooSmall);
+        }
+
+        match &population.cma_es {
+            Some(state) if state.dimension() != population.members[0].total_weights() => {
+                return with_last_error(BrainsError::PopulationConfigMismatch);
+            }
+            Some(_) => {}
+            None => {
+                population.cma_es = Some(cma_es::CmaEs::from_population(
+                    &population.members,
+                    settings,
+                ))
+            }
+        }
+    }
+
+    if config.as_ref().and_then(|c| c.openai_es()).is_some() {
+        match &population.openai_es {
+            Some(state) if state.dimension() != population.members[0].total_weights() => {
+                return with_last_error(BrainsError::PopulationConfigMismatch);
+            }
+            Some(_) => {}
+            None => {
+                population.openai_es =
+                    Some(openai_es::OpenAiEs::from_population(&population.members))
+            }
+        }
+    }
+
+    if config
+        .as_ref()
+        .and_then(|c| c.differential_evolution())
+        .is_some()
+    {
+        let state = population
+            .differential_evolution
+            .get_or_insert_with(Default::default);
+
+        if !state.matches(&population.members[0]) {
+            return with_last_error(BrainsError::PopulationConfigMismatch);
+        }
+    }
+
     *count = population.members.len();
     *inputs = population.members[0].input_count();
     *outputs = population.members[0].output_count();
@@ -262,25 +474,416 @@ pub unsafe extern "C" fn evolve_population(
         Some(f) => slice::from_raw_parts(f.as_ptr(), population.members.len()),
         None => return with_last_error(BrainsError::FitnessPointerNull),
     };
+
+    evolve_members(population, fitness, fitness)
+}
+
+/// Like `evolve_population`, but selects on novelty, or on a blend of novelty and fitness, as
+/// configured. `descriptors` holds one behaviour descriptor of `descriptor_len` values per member,
+/// in member order.
+///
+/// # Safety
+///
+/// `population` must be a live population from `build_population_from_config` or
+/// `load_existing_population`. `fitness` must point to one value per member and `descriptors` to
+/// `descriptor_len` values per member.
+#[no_mangle]
+pub unsafe extern "C" fn evolve_population_with_behavior(
+    population: Option<&mut Population>,
+    fitness: Option<NonNull<c_double>>,
+    descriptors: Option<NonNull<c_double>>,
+    descriptor_len: usize,
+) -> BrainsError {
+    let population = match population {
+        Some(x) => x,
+        None => return with_last_error(BrainsError::PopulationPointerNull),
+    };
+    let fitness = match fitness {
+        Some(f) => slice::from_raw_parts(f.as_ptr(), population.members.len()),
+        None => return with_last_error(BrainsError::FitnessPointerNull),
+    };
+    let descriptors = match descriptors {
+        Some(d) => slice::from_raw_parts(d.as_ptr(), population.members.len() * descriptor_len),
+        None => return with_last_error(BrainsError::DescriptorsPointerNull),
+    };
+    let settings = match population.config.as_ref().map(|c| c.novelty()) {
+        Some(Some(n)) => n.clone(),
+        Some(None) => return with_last_error(BrainsError::NoveltySearchDisabled),
+        None => return with_last_error(BrainsError::MissingEvolutionConfig),
+    };
+
+    let (scores, additions) =
+        match population
+            .novelty_archive
+            .score(fitness, descriptors, descriptor_len, &settings)
+        {
+            Ok(s) => s,
+            Err(e) => return with_last_error(e),
+        };
+
+    match evolve_members(population, fitness, &scores) {
+        BrainsError::None => {
+            population.novelty_archive.add(additions, &settings);
+            BrainsError::None
+        }
+        e => e,
+    }
+}
+
+/// Multi-objective evolution with NSGA-II. `objectives` holds `objective_count` values per member,
+/// back to back, all of which are maximized. The best members by Pareto rank and crowding
+/// distance, up to the configured population size, become parents. They stay in the population
+/// together with as many children, so the member count doubles after the first generation.
+///
+/// # Safety
+///
+/// `population` must be a live population from `build_population_from_config` or
+/// `load_existing_population`. `objectives` must point to `objective_count` values per member.
+#[no_mangle]
+pub unsafe extern "C" fn evolve_population_multi_objective(
+    population: Option<&mut Population>,
+    objectives: Option<NonNull<c_double>>,
+    objective_count: usize,
+) -> BrainsError {
+    let population = match population {
+        Some(x) => x,
+        None => return with_last_error(BrainsError::PopulationPointerNull),
+    };
+    let objectives = match objectives {
+        Some(o) => slice::from_raw_parts(o.as_ptr(), population.members.len() * objective_count),
+        None => return with_last_error(BrainsError::ObjectivesPointerNull),
+    };
     let config = match &population.config {
         Some(c) => c,
         None => return with_last_error(BrainsError::MissingEvolutionConfig),
     };
 
+    if objective_count == 0 {
+        return with_last_error(BrainsError::ObjectiveCountZero);
+    }
+
+    if !config.optimizer().is_genetic_algorithm() {
+        return with_last_error(BrainsError::UnsupportedByOptimizer);
+    }
+
     let mut rng = thread_rng();
 
-    let next_gen = gen::evolve(
+    let next_gen = match gen::evolve_nsga2(
         &mut rng,
         &population.members,
-        fitness,
-        config.selection_method(),
-        config.elitism(),
-        2,
-        nn::gen::crossover,
-        config.crossover_settings(),
-        nn::gen::mutate,
-        config.mutation_settings(),
-    );
+        objectives,
+        objective_count,
+        config.template().population_size,
+        &gen::Breeding {
+            selection_method: config.selection_method(),
+            generation: population.generation,
+            elitism: config.elitism(),
+            crossover_inputs: config.crossover_settings().parents(),
+            crossover: nn::gen::crossover,
+            crossover_settings: config.crossover_settings(),
+            mutate: nn::gen::mutate,
+            mutate_settings: &*population
+                .mutation_controller
+                .apply(config.mutation_settings()),
+        },
+    ) {
+        Ok(n) => n,
+        Err(e) => return with_last_error(e),
+    };
+
+    population.generation += 1;
+    population.members = next_gen;
+
+    BrainsError::None
+}
+
+/// Inserts every evaluated member into the MAP-Elites archive. `descriptors` holds one value per
+/// configured behaviour dimension and member, back to back. `inserted` receives the number of
+/// cells that got a new elite.
+///
+/// # Safety
+///
+/// `population` must be a live population from `build_population_from_config` or
+/// `load_existing_population`. `fitness` must point to one value per member and `descriptors` to
+/// one value per behaviour dimension and member.
+#[no_mangle]
+pub unsafe extern "C" fn map_elites_insert(
+    population: Option<&mut Population>,
+    fitness: Option<NonNull<c_double>>,
+    descriptors: Option<NonNull<c_double>>,
+    inserted: Option<&mut usize>,
+) -> BrainsError {
+    let population = match population {
+        Some(x) => x,
+        None => return with_last_error(BrainsError::PopulationPointerNull),
+    };
+    let settings = match population.config.as_ref().map(|c| c.map_elites()) {
+        Some(Some(s)) => s,
+        Some(None) => return with_last_error(BrainsError::MapElitesDisabled),
+        None => return with_last_error(BrainsError::MissingEvolutionConfig),
+    };
+    let fitness = match fitness {
+        Some(f) => slice::from_raw_parts(f.as_ptr(), population.members.len()),
+        None => return with_last_error(BrainsError::FitnessPointerNull),
+    };
+    let descriptors = match descriptors {
+        Some(d) => slice::from_raw_parts(
+            d.as_ptr(),
+            population.members.len() * settings.dimensions.len(),
+        ),
+        None => return with_last_error(BrainsError::DescriptorsPointerNull),
+    };
+
+    let count = match population
+        .map_elites
+        .get_or_insert_with(|| map_elites::MapElitesArchive::new(settings))
+        .insert(&population.members, fitness, descriptors, settings)
+    {
+        Ok(c) => c,
+        Err(e) => return with_last_error(e),
+    };
+
+    if let Some(inserted) = inserted {
+        *inserted = count;
+    }
+
+    BrainsError::None
+}
+
+/// Replaces all members with `count` new candidates bred from the MAP-Elites archive
+///
+/// # Safety
+///
+/// `population` must be a live population from `build_population_from_config` or
+/// `load_existing_population`.
+#[no_mangle]
+pub unsafe extern "C" fn map_elites_sample(
+    population: Option<&mut Population>,
+    count: usize,
+) -> BrainsError {
+    let population = match population {
+        Some(x) => x,
+        None => return with_last_error(BrainsError::PopulationPointerNull),
+    };
+    let config = match &population.config {
+        Some(c) => c,
+        None => return with_last_error(BrainsError::MissingEvolutionConfig),
+    };
+    let archive = match &population.map_elites {
+        Some(a) => a,
+        None => return with_last_error(BrainsError::MapElitesArchiveEmpty),
+    };
+
+    if count == 0 {
+        return with_last_error(BrainsError::SampleCountZero);
+    }
+
+    if !config.optimizer().is_genetic_algorithm() {
+        return with_last_error(BrainsError::UnsupportedByOptimizer);
+    }
+
+    let candidates = match archive.sample(
+        &mut thread_rng(),
+        count,
+        &gen::Breeding {
+            selection_method: config.selection_method(),
+            generation: population.generation,
+            elitism: 0,
+            crossover_inputs: config.crossover_settings().parents(),
+            crossover: nn::gen::crossover,
+            crossover_settings: config.crossover_settings(),
+            mutate: nn::gen::mutate,
+            mutate_settings: &*population
+                .mutation_controller
+                .apply(config.mutation_settings()),
+        },
+    ) {
+        Ok(c) => c,
+        Err(e) => return with_last_error(e),
+    };
+
+    population.generation += 1;
+    population.members = candidates;
+
+    BrainsError::None
+}
+
+/// Evolves with `selection_fitness` for selection and breeding. Everything that tracks progress
+/// across generations, like the hall of fame and adaptive mutation, sees the raw `fitness`.
+fn evolve_members(
+    population: &mut Population,
+    fitness: &[f64],
+    selection_fitness: &[f64],
+) -> BrainsError {
+    let config = match &population.config {
+        Some(c) => c,
+        None => return with_last_error(BrainsError::MissingEvolutionConfig),
+    };
+
+    let mutation_settings = population
+        .mutation_controller
+        .apply(config.mutation_settings());
+
+    // Updated up front, so that nothing has changed yet if it fails
+    let mutation_controller = match config.adaptive_mutation() {
+        Some(settings) => {
+            let mut controller = population.mutation_controller.clone();
+
+            if let Err(e) = controller.update(fitness, settings) {
+                return with_last_error(e);
+            }
+
+            Some(controller)
+        }
+        None => None,
+    };
+
+    let mut pool = Cow::Borrowed(&population.members[..]);
+    let mut pool_fitness = Cow::Borrowed(selection_fitness);
+
+    // The current members are in the pool already, the hall of fame adds the best earlier ones
+    if config.hall_of_fame().map(|h| h.reinject) == Some(true) {
+        for entry in population.hall_of_fame.entries() {
+            pool.to_mut().push(entry.member.clone());
+            pool_fitness.to_mut().push(entry.fitness);
+        }
+    }
+
+    let shaped_fitness = match gen::shape_fitness(&pool_fitness, config.fitness_shaping()) {
+        Ok(f) => f,
+        Err(e) => return with_last_error(e),
+    };
+
+    let mut rng = thread_rng();
+
+    let breeding = gen::Breeding {
+        selection_method: config.selection_method(),
+        generation: population.generation,
+        elitism: config.elitism(),
+        crossover_inputs: config.crossover_settings().parents(),
+        crossover: nn::gen::crossover,
+        crossover_settings: config.crossover_settings(),
+        mutate: nn::gen::mutate,
+        mutate_settings: &*mutation_settings,
+    };
+
+    let next_gen = match config.generation_scheme() {
+        GenerationScheme::Generational if config.cma_es().is_some() => {
+            let state = population.cma_es.get_or_insert_with(|| {
+                cma_es::CmaEs::from_population(&pool, config.cma_es().unwrap())
+            });
+
+            cma_es::evolve_cma_es(
+                &mut rng,
+                &pool,
+                &shaped_fitness,
+                state,
+                config.template().population_size,
+            )
+        }
+        GenerationScheme::Generational if config.openai_es().is_some() => {
+            let state = population
+                .openai_es
+                .get_or_insert_with(|| openai_es::OpenAiEs::from_population(&pool));
+
+            openai_es::evolve_openai_es(
+                &mut rng,
+                &pool,
+                &shaped_fitness,
+                state,
+                config.openai_es().unwrap(),
+                config.template().population_size,
+            )
+        }
+        GenerationScheme::Generational if config.differential_evolution().is_some() => {
+            differential_evolution::evolve_differential_evolution(
+                &mut rng,
+                &pool,
+                // Trials compete with targets from earlier generations, so shaping can't apply
+                fitness,
+                population
+                    .differential_evolution
+                    .get_or_insert_with(Default::default),
+                config.differential_evolution().unwrap(),
+            )
+        }
+        GenerationScheme::Generational if config.speciation().is_some() => {
+            let settings = config.speciation().unwrap();
+
+            speciation::evolve_speciated(
+                &mut rng,
+                &pool,
+                &shaped_fitness,
+                population
+                    .species
+                    .get_or_insert_with(|| speciation::Species::new(settings)),
+                settings,
+                nn::NeuralNetwork::compatibility_distance,
+                &breeding,
+            )
+        }
+        GenerationScheme::Generational if config.islands().is_some() => islands::evolve_islands(
+            &mut rng,
+            &pool,
+            &shaped_fitness,
+            &mut population.islands,
+            config.islands().unwrap(),
+            &breeding,
+        ),
+        GenerationScheme::Generational if config.alps().is_some() => alps::evolve_alps(
+            &mut rng,
+            &pool,
+            &shaped_fitness,
+            &mut population.age_layers,
+            config.alps().unwrap(),
+            &breeding,
+            |rng| random_network(rng, config),
+        ),
+        GenerationScheme::Generational => gen::evolve(&mut rng, &pool, &shaped_fitness, &breeding),
+        GenerationScheme::MuPlusLambda { offspring } => gen::evolve_mu_lambda(
+            &mut rng,
+            &pool,
+            &shaped_fitness,
+            config.template().population_size,
+            offspring,
+            true,
+            &breeding,
+        ),
+        GenerationScheme::MuCommaLambda { offspring } => gen::evolve_mu_lambda(
+            &mut rng,
+            &pool,
+            &shaped_fitness,
+            config.template().population_size,
+            offspring,
+            false,
+            &breeding,
+        ),
+    };
+
+    let mut next_gen = match next_gen {
+        Ok(n) => n,
+        Err(e) => return with_last_error(e),
+    };
+
+    // Only evolved generations count for the controller, which takes effect from the next one on
+    if let Some(controller) = mutation_controller {
+        population.mutation_controller = controller;
+    }
+
+    if let Some(settings) = config.hall_of_fame() {
+        population.hall_of_fame.update(
+            &population.members,
+            fitness,
+            population.generation,
+            settings.size,
+            nn::NeuralNetwork::fingerprint,
+        );
+    }
+
+    // Reinjected members only take part in breeding, they don't take up any slots
+    if let GenerationScheme::Generational = config.generation_scheme() {
+        next_gen.truncate(population.members.len());
+    }
 
     population.generation += 1;
 
@@ -290,6 +893,300 @@ pub unsafe extern "C" fn evolve_population(
     BrainsError::None
 }
 
+/// Replaces only the `k` weakest members with offspring. `replaced_indices` must have room for
+/// `k` entries and receives the indices of the replaced members. The generation counter goes up
+/// whenever as many members have been replaced as the population holds, which is also when the
+/// adaptive mutation controller sees `fitness`. Speciation, islands and ALPS track every member's
+/// place, so they don't work with partial replacement.
+///
+/// # Safety
+///
+/// `population` must be a live population from `build_population_from_config` or
+/// `load_existing_population`. `fitness` must point to one value per member and `replaced_indices`
+/// to room for `k` values.
+#[no_mangle]
+pub unsafe extern "C" fn evolve_steady_state(
+    population: Option<&mut Population>,
+    fitness: Option<NonNull<c_double>>,
+    k: usize,
+    replaced_indices: Option<NonNull<usize>>,
+) -> BrainsError {
+    let population = match population {
+        Some(x) => x,
+        None => return with_last_error(BrainsError::PopulationPointerNull),
+    };
+    let fitness = match fitness {
+        Some(f) => slice::from_raw_parts(f.as_ptr(), population.members.len()),
+        None => return with_last_error(BrainsError::FitnessPointerNull),
+    };
+
+    if k == 0 || k > population.members.len() {
+        return with_last_error(BrainsError::InvalidReplacementCount);
+    }
+
+    let replaced_indices = match replaced_indices {
+        Some(r) => slice::from_raw_parts_mut(r.as_ptr(), k),
+        None => return with_last_error(BrainsError::ReplacedIndicesPointerNull),
+    };
+    let config = match &population.config {
+        Some(c) => c,
+        None => return with_last_error(BrainsError::MissingEvolutionConfig),
+    };
+
+    if !config.optimizer().is_genetic_algorithm() {
+        return with_last_error(BrainsError::UnsupportedByOptimizer);
+    }
+
+    if config.speciation().is_some() || config.islands().is_some() || config.alps().is_some() {
+        return with_last_error(BrainsError::SteadyStateUnsupportedSettings);
+    }
+
+    let shaped_fitness = match gen::shape_fitness(fitness, config.fitness_shaping()) {
+        Ok(f) => f,
+        Err(e) => return with_last_error(e),
+    };
+
+    let replacements = population.steady_state_replacements + k;
+
+    // The controller only looks at one fitness record per generation. It's updated before the
+    // members are replaced, so that nothing has changed yet if it fails.
+    let mutation_controller = match config.adaptive_mutation() {
+        Some(settings) if replacements >= population.members.len() => {
+            let mut controller = population.mutation_controller.clone();
+
+            if let Err(e) = controller.update(fitness, settings) {
+                return with_last_error(e);
+            }
+
+            Some(controller)
+        }
+        _ => None,
+    };
+
+    let mut rng = thread_rng();
+    let mut replaced = Vec::with_capacity(k);
+
+    if let Err(e) = gen::evolve_steady_state(
+        &mut rng,
+        &mut population.members,
+        &shaped_fitness,
+        k,
+        &gen::Breeding {
+            selection_method: config.selection_method(),
+            generation: population.generation,
+            elitism: 0,
+            crossover_inputs: config.crossover_settings().parents(),
+            crossover: nn::gen::crossover,
+            crossover_settings: config.crossover_settings(),
+            mutate: nn::gen::mutate,
+            mutate_settings: &*population
+                .mutation_controller
+                .apply(config.mutation_settings()),
+        },
+        &mut replaced,
+    ) {
+        return with_last_error(e);
+    }
+
+    if let Some(controller) = mutation_controller {
+        population.mutation_controller = controller;
+    }
+
+    population.generation += replacements / population.members.len();
+    population.steady_state_replacements = replacements % population.members.len();
+
+    replaced_indices.copy_from_slice(&replaced);
+
+    BrainsError::None
+}
+
+/// Number of members that need to be evaluated in the current round. Can change after evolving,
+/// depending on the generation scheme.
+///
+/// # Safety
+///
+/// `population` must be a live population from `build_population_from_config` or
+/// `load_existing_population`.
+#[no_mangle]
+pub unsafe extern "C" fn get_member_count(
+    population: Option<&Population>,
+    count: Option<&mut usize>,
+) -> BrainsError {
+    let population = match population {
+        Some(p) => p,
+        None => return with_last_error(BrainsError::PopulationPointerNull),
+    };
+
+    match count {
+        Some(c) => *c = population.members.len(),
+        None => return with_last_error(BrainsError::CountPointerNull),
+    }
+
+    BrainsError::None
+}
+
+/// Writes the species ID of every member into `species_ids`, which must have room for one entry
+/// per member. Only available if speciation is enabled in the config.
+///
+/// # Safety
+///
+/// `population` must be a live population from `build_population_from_config` or
+/// `load_existing_population`. `species_ids` must point to room for one value per member.
+#[no_mangle]
+pub unsafe extern "C" fn get_species_ids(
+    population: Option<&Population>,
+    species_ids: Option<NonNull<usize>>,
+) -> BrainsError {
+    let population = match population {
+        Some(p) => p,
+        None => return with_last_error(BrainsError::PopulationPointerNull),
+    };
+
+    let species_ids = match species_ids {
+        Some(s) => slice::from_raw_parts_mut(s.as_ptr(), population.members.len()),
+        None => return with_last_error(BrainsError::SpeciesIdsPointerNull),
+    };
+
+    match &population.species {
+        Some(species) if species.is_assigned_to(&population.members) => {
+            species_ids.copy_from_slice(species.member_species());
+            BrainsError::None
+        }
+        _ => with_last_error(BrainsError::SpeciationDisabled),
+    }
+}
+
+/// Writes for every index whether the trial evaluated there in the last generation replaced its
+/// target. The current members are the next trials, which haven't competed yet, so this reports
+/// on the members as they were before the last evolution. `replaced` must have room for one entry
+/// per member.
+///
+/// # Safety
+///
+/// `population` must be a live population from `build_population_from_config` or
+/// `load_existing_population`. `replaced` must point to room for one value per member.
+#[no_mangle]
+pub unsafe extern "C" fn get_trial_replacements(
+    population: Option<&Population>,
+    replaced: Option<NonNull<bool>>,
+) -> BrainsError {
+    let population = match population {
+        Some(p) => p,
+        None => return with_last_error(BrainsError::PopulationPointerNull),
+    };
+
+    let replaced = match replaced {
+        Some(r) => slice::from_raw_parts_mut(r.as_ptr(), population.members.len()),
+        None => return with_last_error(BrainsError::TrialReplacementsPointerNull),
+    };
+
+    match &population.differential_evolution {
+        Some(state) if state.replaced().len() == replaced.len() => {
+            replaced.copy_from_slice(state.replaced());
+            BrainsError::None
+        }
+        Some(_) => {
+            // Nothing was selected yet
+            replaced.iter_mut().for_each(|r| *r = false);
+            BrainsError::None
+        }
+        None => with_last_error(BrainsError::DifferentialEvolutionDisabled),
+    }
+}
+
+/// Writes the mutation probability and weights affected ratios that are currently in effect, and
+/// the factor by which the adaptive mutation controller scaled them. A scale above 1 means the
+/// controller raised mutation because evolution stagnated.
+///
+/// # Safety
+///
+/// `population` must be a live population from `build_population_from_config` or
+/// `load_existing_population`.
+#[no_mangle]
+pub unsafe extern "C" fn get_effective_mutation(
+    population: Option<&Population>,
+    mutation_probability: Option<&mut c_double>,
+    min_weights_affected_ratio: Option<&mut c_double>,
+    max_weights_affected_ratio: Option<&mut c_double>,
+    scale: Option<&mut c_double>,
+) -> BrainsError {
+    let population = match population {
+        Some(p) => p,
+        None => return with_last_error(BrainsError::PopulationPointerNull),
+    };
+
+    let config = match &population.config {
+        Some(c) => c,
+        None => return with_last_error(BrainsError::MissingEvolutionConfig),
+    };
+
+    match (
+        mutation_probability,
+        min_weights_affected_ratio,
+        max_weights_affected_ratio,
+        scale,
+    ) {
+        (Some(probability), Some(min_ratio), Some(max_ratio), Some(scale)) => {
+            let settings = population
+                .mutation_controller
+                .apply(config.mutation_settings());
+
+            *probability = settings.mutation_probability();
+            *min_ratio = settings.min_weights_affected_ratio();
+            *max_ratio = settings.max_weights_affected_ratio();
+            *scale = population.mutation_controller.scale();
+
+            BrainsError::None
+        }
+        _ => with_last_error(BrainsError::EffectiveMutationPointerNull),
+    }
+}
+
+/// Writes the self-adaptive mutation step sizes of the member at `index` into `sigmas`, which has
+/// room for `capacity` values. `count` receives the number of step sizes the member carries,
+/// which is 0 if self-adaptation isn't used and may exceed `capacity`.
+///
+/// # Safety
+///
+/// `population` must be a live population from `build_population_from_config` or
+/// `load_existing_population`. `sigmas` must point to room for `capacity` values.
+#[no_mangle]
+pub unsafe extern "C" fn get_mutation_sigmas(
+    population: Option<&Population>,
+    index: usize,
+    sigmas: Option<NonNull<c_double>>,
+    capacity: usize,
+    count: Option<&mut usize>,
+) -> BrainsError {
+    let population = match population {
+        Some(p) => p,
+        None => return with_last_error(BrainsError::PopulationPointerNull),
+    };
+
+    let network = match population.members.get(index) {
+        Some(n) => n,
+        None => return with_last_error(BrainsError::InvalidMemberIndex),
+    };
+
+    let sigmas = match sigmas {
+        Some(s) => slice::from_raw_parts_mut(s.as_ptr(), capacity),
+        None => return with_last_error(BrainsError::MutationSigmasPointerNull),
+    };
+
+    let count = match count {
+        Some(c) => c,
+        None => return with_last_error(BrainsError::CountPointerNull),
+    };
+
+    let member_sigmas = network.mutation_sigmas();
+    let copied = member_sigmas.len().min(capacity);
+
+    sigmas[..copied].copy_from_slice(&member_sigmas[..copied]);
+    *count = member_sigmas.len();
+
+    BrainsError::None
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn drop_population(population: Option<NonNull<Population>>) -> BrainsError {
     match population {
@@ -343,15 +1240,154 @@ pub unsafe extern "C" fn save_top_n(
 
     members.sort_unstable_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
 
+    // The central network of OpenAI-ES is its champion, even though it is never evaluated itself
+    let champion = population
+        .openai_es
+        .as_ref()
+        .map(|state| state.champion(&population.members[0]));
+
+    let members = champion
+        .into_iter()
+        .chain(members.into_iter().map(|m| m.0))
+        .take(n)
+        .collect::<Vec<_>>();
+
     let json = match serde_json::to_string_pretty(&Population {
-        members: members
+        generation: population.generation,
+        ..Population::new(members, None)
+    }) {
+        Ok(j) => j,
+        Err(e) => {
+            return with_last_error_extended(BrainsError::InternalError, e);
+        }
+    };
+
+    match fs::write(path, json) {
+        Ok(_) => BrainsError::None,
+        Err(e) => {
+            return with_last_error_extended(BrainsError::FileSaveError, e);
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ParetoFront<'a> {
+    members: Vec<&'a nn::NeuralNetwork>,
+    generation: usize,
+    /// Objective values of every member, in member order
+    objectives: Vec<&'a [f64]>,
+}
+
+/// Saves all members on the first Pareto front, together with their objective values. The file
+/// can be loaded like any other population file.
+///
+/// # Safety
+///
+/// `path` must be a null-terminated string. `population` must be a live population from
+/// `build_population_from_config` or `load_existing_population`. `objectives` must point to
+/// `objective_count` values per member.
+#[no_mangle]
+pub unsafe extern "C" fn save_pareto_front(
+    path: Option<NonNull<c_char>>,
+    population: Option<&Population>,
+    objectives: Option<NonNull<c_double>>,
+    objective_count: usize,
+) -> BrainsError {
+    let path = match path {
+        Some(p) => match CStr::from_ptr(p.as_ptr()).to_str() {
+            Ok(p) => p,
+            Err(e) => {
+                return with_last_error_extended(BrainsError::InvalidOutputPath, e);
+            }
+        },
+        None => return with_last_error(BrainsError::OutputPathNull),
+    };
+
+    let population = match population {
+        Some(p) => p,
+        None => return with_last_error(BrainsError::PopulationPointerNull),
+    };
+
+    let objectives = match objectives {
+        Some(o) => slice::from_raw_parts(o.as_ptr(), population.members.len() * objective_count),
+        None => return with_last_error(BrainsError::ObjectivesPointerNull),
+    };
+
+    if objective_count == 0 {
+        return with_last_error(BrainsError::ObjectiveCountZero);
+    }
+
+    if let Err(e) = gen::validate_fitness(objectives) {
+        return with_last_error(e);
+    }
+
+    let front = gen::non_dominated_sort(objectives, objective_count)
+        .into_iter()
+        .next()
+        .unwrap_or_default();
+
+    let json = match serde_json::to_string_pretty(&ParetoFront {
+        members: front.iter().map(|&idx| &population.members[idx]).collect(),
+        generation: population.generation,
+        objectives: front
             .iter()
-            .take(n)
-            .map(|m| &m.0)
+            .map(|&idx| &objectives[idx * objective_count..(idx + 1) * objective_count])
+            .collect(),
+    }) {
+        Ok(j) => j,
+        Err(e) => {
+            return with_last_error_extended(BrainsError::InternalError, e);
+        }
+    };
+
+    match fs::write(path, json) {
+        Ok(_) => BrainsError::None,
+        Err(e) => with_last_error_extended(BrainsError::FileSaveError, e),
+    }
+}
+
+/// Saves every elite of the MAP-Elites archive as a population file
+///
+/// # Safety
+///
+/// `path` must be a null-terminated string. `population` must be a live population from
+/// `build_population_from_config` or `load_existing_population`.
+#[no_mangle]
+pub unsafe extern "C" fn save_map_elites_archive(
+    path: Option<NonNull<c_char>>,
+    population: Option<&Population>,
+) -> BrainsError {
+    let path = match path {
+        Some(p) => match CStr::from_ptr(p.as_ptr()).to_str() {
+            Ok(p) => p,
+            Err(e) => {
+                return with_last_error_extended(BrainsError::InvalidOutputPath, e);
+            }
+        },
+        None => return with_last_error(BrainsError::OutputPathNull),
+    };
+
+    let population = match population {
+        Some(p) => p,
+        None => return with_last_error(BrainsError::PopulationPointerNull),
+    };
+
+    let members = match &population.map_elites {
+        Some(archive) => archive
+            .elites()
+            .map(|(e, _)| e)
             .cloned()
             .collect::<Vec<_>>(),
+        None => return with_last_error(BrainsError::MapElitesArchiveEmpty),
+    };
+
+    if members.is_empty() {
+        return with_last_error(BrainsError::MapElitesArchiveEmpty);
+    }
+
+    let json = match serde_json::to_string_pretty(&Population {
         generation: population.generation,
-        config: None,
+        ..Population::new(members, None)
     }) {
         Ok(j) => j,
         Err(e) => {
@@ -361,9 +1397,68 @@ pub unsafe extern "C" fn save_top_n(
 
     match fs::write(path, json) {
         Ok(_) => BrainsError::None,
+        Err(e) => with_last_error_extended(BrainsError::FileSaveError, e),
+    }
+}
+
+#[derive(Serialize)]
+struct HallOfFameExport<'a> {
+    members: Vec<&'a nn::NeuralNetwork>,
+    generation: usize,
+    /// Best fitness of every member, in member order
+    fitness: Vec<f64>,
+    /// Generation in which every member made it into the hall of fame, in member order
+    birth_generations: Vec<usize>,
+}
+
+/// Saves the hall of fame, best member first. The file can be loaded like any other population
+/// file.
+///
+/// # Safety
+///
+/// `path` must be a null-terminated string. `population` must be a live population from
+/// `build_population_from_config` or `load_existing_population`.
+#[no_mangle]
+pub unsafe extern "C" fn save_hall_of_fame(
+    path: Option<NonNull<c_char>>,
+    population: Option<&Population>,
+) -> BrainsError {
+    let path = match path {
+        Some(p) => match CStr::from_ptr(p.as_ptr()).to_str() {
+            Ok(p) => p,
+            Err(e) => {
+                return with_last_error_extended(BrainsError::InvalidOutputPath, e);
+            }
+        },
+        None => return with_last_error(BrainsError::OutputPathNull),
+    };
+
+    let population = match population {
+        Some(p) => p,
+        None => return with_last_error(BrainsError::PopulationPointerNull),
+    };
+
+    let entries = population.hall_of_fame.entries();
+
+    if entries.is_empty() {
+        return with_last_error(BrainsError::HallOfFameEmpty);
+    }
+
+    let json = match serde_json::to_string_pretty(&HallOfFameExport {
+        members: entries.iter().map(|e| &e.member).collect(),
+        generation: population.generation,
+        fitness: entries.iter().map(|e| e.fitness).collect(),
+        birth_generations: entries.iter().map(|e| e.generation).collect(),
+    }) {
+        Ok(j) => j,
         Err(e) => {
-            return with_last_error_extended(BrainsError::FileSaveError, e);
+            return with_last_error_extended(BrainsError::InternalError, e);
         }
+    };
+
+    match fs::write(path, json) {
+        Ok(_) => BrainsError::None,
+        Err(e) => with_last_error_extended(BrainsError::FileSaveError, e),
     }
 }
 
@@ -0,0 +1,247 @@
+use crate::{
+    error::BrainsError,
+    gen::{self, Breeding, SelectionMethod, SpecimenWriter},
+};
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct MapElitesSettings {
+    pub dimensions: Vec<BehaviorDimension>,
+}
+
+impl Default for MapElitesSettings {
+    fn default() -> Self {
+        MapElitesSettings {
+            dimensions: vec![
+                BehaviorDimension {
+                    min: 0.0,
+                    max: 1.0,
+                    bins: 10,
+                },
+                BehaviorDimension {
+                    min: 0.0,
+                    max: 1.0,
+                    bins: 10,
+                },
+            ],
+        }
+    }
+}
+
+/// One axis of the elite grid. Values outside of [min, max] end up in the outermost bins.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub struct BehaviorDimension {
+    pub min: f64,
+    pub max: f64,
+    pub bins: usize,
+}
+
+impl MapElitesSettings {
+    pub fn validate(&self) -> Result<(), BrainsError> {
+        if self.dimensions.is_empty() {
+            return Err(BrainsError::MapElitesNoDimensions);
+        }
+
+        if self
+            .dimensions
+            .iter()
+            .any(|d| d.bins == 0 || d.max <= d.min)
+        {
+            return Err(BrainsError::MapElitesInvalidDimension);
+        }
+
+        Ok(())
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.dimensions.iter().map(|d| d.bins).product()
+    }
+
+    fn cell_index(&self, descriptor: &[f64]) -> usize {
+        self.dimensions
+            .iter()
+            .zip(descriptor)
+            .fold(0, |index, (dimension, &value)| {
+                let ratio = (value - dimension.min) / (dimension.max - dimension.min);
+                let bin = ((ratio * dimension.bins as f64).floor().max(0.0) as usize)
+                    .min(dimension.bins - 1);
+
+                index * dimension.bins + bin
+            })
+    }
+}
+
+/// Grid of the fittest member found so far for every combination of behaviour bins
+#[derive(Deserialize, Serialize, Clone)]
+pub struct MapElitesArchive<S> {
+    /// Grid the cells were laid out for
+    dimensions: Vec<BehaviorDimension>,
+    cells: Vec<Option<(S, f64)>>,
+}
+
+impl<S: Clone> MapElitesArchive<S> {
+    pub fn new(settings: &MapElitesSettings) -> MapElitesArchive<S> {
+        MapElitesArchive {
+            dimensions: settings.dimensions.clone(),
+            cells: vec![None; settings.cell_count()],
+        }
+    }
+
+    /// Whether the archive was laid out for the same grid
+    pub fn matches(&self, settings: &MapElitesSettings) -> bool {
+        self.dimensions == settings.dimensions
+    }
+
+    /// Puts every member into the cell of its behaviour descriptor, if the cell is empty or holds
+    /// a less fit elite. `descriptors` holds one value per dimension and member, back to back.
+    /// Returns the number of cells that got a new elite.
+    pub fn insert(
+        &mut self,
+        population: &[S],
+        fitness: &[f64],
+        descriptors: &[f64],
+        settings: &MapElitesSettings,
+    ) -> Result<usize, BrainsError> {
+        let dimensions = settings.dimensions.len();
+
+        assert_eq!(population.len(), fitness.len());
+        assert_eq!(population.len() * dimensions, descriptors.len());
+
+        gen::validate_fitness(fitness)?;
+        gen::validate_fitness(descriptors)?;
+
+        let mut updated_cells = Vec::new();
+
+        for ((specimen, &f), descriptor) in population
+            .iter()
+            .zip(fitness)
+            .zip(descriptors.chunks_exact(dimensions))
+        {
+            let index = settings.cell_index(descriptor);
+            let cell = &mut self.cells[index];
+
+            if cell.as_ref().map(|(_, elite)| f > *elite).unwrap_or(true) {
+                *cell = Some((specimen.clone(), f));
+                updated_cells.push(index);
+            }
+        }
+
+        // Later members of the batch can take a cell over again, which still counts once
+        updated_cells.sort_unstable();
+        updated_cells.dedup();
+
+        Ok(updated_cells.len())
+    }
+
+    /// All elites, in grid order
+    pub fn elites(&self) -> impl Iterator<Item = &(S, f64)> {
+        self.cells.iter().filter_map(|c| c.as_ref())
+    }
+
+    /// Creates `count` new candidates from uniformly chosen elites by crossover and mutation.
+    /// The selection method and elitism of `breeding` don't apply.
+    pub fn sample<R, C, CS, M, MS>(
+        &self,
+        rng: &mut R,
+        count: usize,
+        breeding: &Breeding<C, CS, M, MS>,
+    ) -> Result<Vec<S>, BrainsError>
+    where
+        R: Rng + ?Sized,
+        C: Fn(&mut R, &[&S], &[f64], &mut SpecimenWriter<S>, &CS, &mut Vec<usize>),
+        M: Fn(&mut R, &mut S, &MS),
+    {
+        let (elites, fitness): (Vec<_>, Vec<_>) = self.elites().cloned().unzip();
+
+        if elites.is_empty() {
+            return Err(BrainsError::MapElitesArchiveEmpty);
+        }
+
+        let mut output = Vec::with_capacity(count);
+
+        gen::breed(
+            rng,
+            &elites,
+            &fitness,
+            &Breeding {
+                selection_method: SelectionMethod::Uniform,
+                ..breeding.by_ref()
+            },
+            &mut SpecimenWriter::new(count, &mut output),
+        )?;
+
+        output.iter_mut().for_each(|s| breeding.mutate(rng, s));
+
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(bins: usize) -> MapElitesSettings {
+        MapElitesSettings {
+            dimensions: vec![
+                BehaviorDimension {
+                    min: 0.0,
+                    max: 1.0,
+                    bins,
+                },
+                BehaviorDimension {
+                    min: 0.0,
+                    max: 1.0,
+                    bins: 2,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn cells_keep_fittest_member() {
+        let settings = settings(2);
+        let mut archive = MapElitesArchive::new(&settings);
+
+        let inserted = archive
+            .insert(
+                &[1.0, 2.0, 3.0, 4.0],
+                &[1.0, 2.0, 3.0, 0.5],
+                &[0.1, 0.1, 0.2, 0.2, 0.3, 0.3, 0.9, 0.9],
+                &settings,
+            )
+            .unwrap();
+
+        // The first cell is taken over twice within the batch, but counts once
+        assert_eq!(inserted, 2);
+        assert_eq!(
+            archive.elites().cloned().collect::<Vec<_>>(),
+            vec![(3.0, 3.0), (4.0, 0.5)]
+        );
+
+        let inserted = archive
+            .insert(&[5.0, 6.0], &[2.5, 1.0], &[0.0, 0.0, 0.9, 0.1], &settings)
+            .unwrap();
+
+        assert_eq!(inserted, 1);
+        assert_eq!(archive.elites().count(), 3);
+    }
+
+    #[test]
+    fn matches_compares_the_grid() {
+        let archive = MapElitesArchive::<f64>::new(&settings(4));
+
+        let mut widened = settings(4);
+        widened.dimensions[0].max = 2.0;
+        let swapped = MapElitesSettings {
+            dimensions: settings(4).dimensions.into_iter().rev().collect(),
+        };
+
+        assert!(archive.matches(&settings(4)));
+        assert!(!archive.matches(&settings(3)));
+
+        // Same number of cells, laid out differently
+        assert!(!archive.matches(&widened));
+        assert!(!archive.matches(&swapped));
+    }
+}
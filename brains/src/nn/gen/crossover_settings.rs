@@ -6,6 +6,13 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Serialize, Clone)]
 pub struct CrossoverSettingsTemplate {
+    /// Number of parents selected for every crossover, each of which yields one child
+    #[serde(default = "default_parent_count")]
+    pub parents: usize,
+    /// Aligns the hidden nodes of every parent to those of the first one before recombining, so
+    /// nodes that compute the same feature in a different position end up at the same place
+    #[serde(default)]
+    pub align_nodes: bool,
     pub min_nodes_affected_ratio: f64,
     pub max_nodes_affected_ratio: f64,
     pub methods: Vec<CrossoverMethodProbability>,
@@ -14,6 +21,8 @@ pub struct CrossoverSettingsTemplate {
 impl Default for CrossoverSettingsTemplate {
     fn default() -> Self {
         CrossoverSettingsTemplate {
+            parents: default_parent_count(),
+            align_nodes: false,
             min_nodes_affected_ratio: 0.02,
             max_nodes_affected_ratio: 0.3,
             methods: vec![
@@ -33,6 +42,10 @@ impl Default for CrossoverSettingsTemplate {
     }
 }
 
+fn default_parent_count() -> usize {
+    2
+}
+
 #[derive(Deserialize, Serialize, Clone)]
 pub struct CrossoverMethodProbability {
     pub method: CrossoverMethod,
@@ -40,11 +53,16 @@ pub struct CrossoverMethodProbability {
 }
 
 pub struct CrossoverSettings {
+    parents: usize,
+    align_nodes: bool,
     min_nodes_affected: usize,
     max_nodes_affected: usize,
     node_index_buffer: Box<[usize]>,
     methods: Vec<CrossoverMethod>,
     method_index: WeightedIndex<f64>,
+    node_methods: Vec<CrossoverMethod>,
+    /// None if there are no node methods with a positive probability
+    node_method_index: Option<WeightedIndex<f64>>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Copy)]
@@ -54,6 +72,46 @@ pub enum CrossoverMethod {
         min_weights_swapped_ratio: f64,
         max_weights_swapped_ratio: f64,
     },
+    /// Every child takes the node from a uniformly chosen parent
+    UniformNode,
+    /// One child gets the average of the parents' node weights, weighted by their fitness. Falls
+    /// back to equal weights unless all parents have positive fitness.
+    FitnessWeightedAverage,
+    /// Two children become weighted averages of each other's node: a' = alpha * a + (1 - alpha) * b
+    /// and b' = alpha * b + (1 - alpha) * a, with one alpha per node drawn from the range
+    Arithmetic {
+        min_alpha: f64,
+        max_alpha: f64,
+    },
+    /// BLX-alpha: Every weight of two children is drawn uniformly from the interval spanned by
+    /// their values, extended by alpha times its width on both sides
+    BlendAlpha {
+        alpha: f64,
+    },
+    /// Simulated binary crossover (SBX) of two children, applied to each weight with the given
+    /// probability. A larger distribution index `eta` keeps the children closer to their parents.
+    SimulatedBinary {
+        eta: f64,
+        probability: f64,
+    },
+    /// Two children swap all weights of one random layer, once per crossover
+    SwapLayer,
+    /// Two children swap every other segment of their flattened genomes, split at `points`
+    /// random positions, once per crossover. Segments follow the layer order, so neighbouring
+    /// nodes mostly stay together.
+    KPoint {
+        points: usize,
+    },
+}
+
+impl CrossoverMethod {
+    /// Whether the method recombines a single node, as opposed to a whole layer or genome
+    pub fn is_node_method(&self) -> bool {
+        !matches!(
+            self,
+            CrossoverMethod::SwapLayer | CrossoverMethod::KPoint { .. }
+        )
+    }
 }
 
 impl CrossoverSettings {
@@ -79,6 +137,14 @@ impl CrossoverSettings {
         )
         .map_err(|_| BrainsError::CrossoverInvalidMethodProbabilities)?;
 
+        let (node_methods, node_probabilities): (Vec<_>, Vec<_>) = template
+            .methods
+            .iter()
+            .filter(|cmp| cmp.method.is_node_method())
+            .map(|cmp| (cmp.method, cmp.relative_probability))
+            .unzip();
+        let node_method_index = WeightedIndex::new(node_probabilities).ok();
+
         // TODO: Make this work for non-uniform node weight counts across a layer
         let max_node_weights = nn
             .layers()
@@ -91,27 +157,51 @@ impl CrossoverSettings {
         let node_index_buffer = (0..max_node_weights).collect::<Box<[usize]>>();
 
         Ok(CrossoverSettings {
+            parents: template.parents,
+            align_nodes: template.align_nodes,
             min_nodes_affected,
             max_nodes_affected,
             node_index_buffer,
             methods,
             method_index,
+            node_methods,
+            node_method_index,
         })
     }
 
+    pub fn parents(&self) -> usize {
+        self.parents
+    }
+
+    pub fn align_nodes(&self) -> bool {
+        self.align_nodes
+    }
+
     pub fn gen_nodes_affected<R: Rng + ?Sized>(&self, rng: &mut R) -> usize {
         rng.gen_range(self.min_nodes_affected, self.max_nodes_affected)
     }
 
+    /// Method of a whole crossover, which may be a node method
     pub fn gen_method<R: Rng + ?Sized>(&self, rng: &mut R) -> CrossoverMethod {
         self.methods[self.method_index.sample(rng)]
     }
 
+    /// Method for a single node, only drawn after `gen_method` returned a node method
+    pub fn gen_node_method<R: Rng + ?Sized>(&self, rng: &mut R) -> CrossoverMethod {
+        let index = self.node_method_index.as_ref().unwrap();
+
+        self.node_methods[index.sample(rng)]
+    }
+
     pub fn node_index_buffer(&mut self) -> &mut [usize] {
         &mut self.node_index_buffer[..]
     }
 
     fn validate_template(template: &CrossoverSettingsTemplate) -> Result<(), BrainsError> {
+        if template.parents < 2 {
+            return Err(BrainsError::CrossoverInvalidParentCount);
+        }
+
         if template.min_nodes_affected_ratio < 0.0 || template.min_nodes_affected_ratio > 1.0 {
             return Err(BrainsError::CrossoverInvalidMinNodeRatio);
         }
@@ -128,7 +218,10 @@ impl CrossoverSettings {
 
         for cmp in &template.methods {
             match cmp.method {
-                CrossoverMethod::SwapWholeNode => {}
+                CrossoverMethod::SwapWholeNode
+                | CrossoverMethod::UniformNode
+                | CrossoverMethod::FitnessWeightedAverage
+                | CrossoverMethod::SwapLayer => {}
                 CrossoverMethod::SwapSomeWeights {
                     min_weights_swapped_ratio,
                     max_weights_swapped_ratio,
@@ -141,6 +234,35 @@ impl CrossoverSettings {
                         return Err(BrainsError::CrossoverInvalidSwapWeightsRatios);
                     }
                 }
+                CrossoverMethod::Arithmetic {
+                    min_alpha,
+                    max_alpha,
+                } => {
+                    // Also rejects NaN, which fails every comparison
+                    if !(0.0..=1.0).contains(&min_alpha) || !(min_alpha..=1.0).contains(&max_alpha)
+                    {
+                        return Err(BrainsError::CrossoverInvalidArithmeticAlphas);
+                    }
+                }
+                CrossoverMethod::BlendAlpha { alpha } => {
+                    if !alpha.is_finite() || alpha < 0.0 {
+                        return Err(BrainsError::CrossoverInvalidBlendAlpha);
+                    }
+                }
+                CrossoverMethod::SimulatedBinary { eta, probability } => {
+                    if !eta.is_finite() || eta < 0.0 {
+                        return Err(BrainsError::CrossoverInvalidSbxEta);
+                    }
+
+                    if !(0.0..=1.0).contains(&probability) {
+                        return Err(BrainsError::CrossoverInvalidSbxProbability);
+                    }
+                }
+                CrossoverMethod::KPoint { points } => {
+                    if points == 0 {
+                        return Err(BrainsError::CrossoverKPointCountZero);
+                    }
+                }
             }
         }
 
@@ -5,59 +5,271 @@ pub use crossover_settings::*;
 pub use mutation_settings::*;
 
 use super::NeuralNetwork;
-use crate::gen::SpecimenWriter;
+use crate::gen::{self, SpecimenWriter};
 use rand::prelude::*;
 use std::mem;
 
+/// Crosses over the `input` parents, whose fitness is `input_fitness`, and writes one child per
+/// parent. Every child starts as a copy of its parent, then one method is chosen for the whole
+/// crossover. Layer and genome methods recombine the children once, node methods once per
+/// affected node, at a randomly chosen node and with a method chosen among the node methods each
+/// time.
 pub fn crossover<R: Rng + ?Sized>(
     rng: &mut R,
     input: &[&NeuralNetwork],
+    input_fitness: &[f64],
     output: &mut SpecimenWriter<NeuralNetwork>,
     settings: &CrossoverSettings,
     weight_index_buffer: &mut Vec<usize>,
 ) {
-    debug_assert!(input.len() == 2);
+    debug_assert!(input.len() >= 2);
+    debug_assert_eq!(input.len(), input_fitness.len());
 
     let layers = input[0].layers().len();
 
-    let mut a = input[0].clone();
-    let mut b = input[1].clone();
+    // Node methods also read from the parents, so these are aligned and not just the children
+    let aligned = if settings.align_nodes() {
+        let mut aligned = input.iter().map(|&nn| nn.clone()).collect::<Vec<_>>();
+        let (first, rest) = aligned.split_first_mut().unwrap();
 
-    for _ in 0..settings.gen_nodes_affected(rng) {
-        let layer = rng.gen_range(0, layers);
-        let node = rng.gen_range(0, input[0].layers()[layer].activations().len());
+        for parent in rest {
+            parent.align_to(first);
+        }
 
-        let a_weights = a.layers_mut()[layer].node_weights_mut(node).unwrap();
-        let b_weights = b.layers_mut()[layer].node_weights_mut(node).unwrap();
+        Some(aligned)
+    } else {
+        None
+    };
 
-        match settings.gen_method(rng) {
-            CrossoverMethod::SwapWholeNode => {
-                for (w_a, w_b) in a_weights.iter_mut().zip(b_weights) {
-                    mem::swap(w_a, w_b);
+    let parents = match &aligned {
+        Some(aligned) => aligned.iter().collect::<Vec<_>>(),
+        None => input.to_vec(),
+    };
+
+    let mut children = parents.iter().map(|&nn| nn.clone()).collect::<Vec<_>>();
+
+    match settings.gen_method(rng) {
+        CrossoverMethod::SwapLayer => {
+            let layer = rng.gen_range(0, layers);
+            let (a, b) = children_pair(rng, &mut children);
+
+            a.layers_mut()[layer]
+                .all_weights_mut()
+                .swap_with_slice(b.layers_mut()[layer].all_weights_mut());
+        }
+        CrossoverMethod::KPoint { points } => {
+            let (a, b) = children_pair(rng, &mut children);
+
+            let mut a_genome = a.flattened_weights();
+            let mut b_genome = b.flattened_weights();
+
+            let mut cuts =
+                rand::seq::index::sample(rng, a_genome.len() - 1, points.min(a_genome.len() - 1))
+                    .into_iter()
+                    .map(|c| c + 1)
+                    .collect::<Vec<_>>();
+            cuts.sort_unstable();
+            cuts.push(a_genome.len());
+
+            // Every other segment between two cuts is swapped, starting with the second one
+            for segment in cuts.windows(2).step_by(2) {
+                a_genome[segment[0]..segment[1]]
+                    .swap_with_slice(&mut b_genome[segment[0]..segment[1]]);
+            }
+
+            a.set_flattened_weights(&a_genome);
+            b.set_flattened_weights(&b_genome);
+        }
+        _ => {
+            for _ in 0..settings.gen_nodes_affected(rng) {
+                let layer = rng.gen_range(0, layers);
+                let node = rng.gen_range(0, input[0].layers()[layer].activations().len());
+                let method = settings.gen_node_method(rng);
+
+                cross_node(
+                    rng,
+                    &parents,
+                    input_fitness,
+                    &mut children,
+                    layer,
+                    node,
+                    method,
+                    weight_index_buffer,
+                );
+            }
+        }
+    }
+
+    for child in children {
+        output.write(child);
+    }
+}
+
+/// Recombines a single node of the children with a node method
+#[allow(clippy::too_many_arguments)]
+fn cross_node<R: Rng + ?Sized>(
+    rng: &mut R,
+    parents: &[&NeuralNetwork],
+    input_fitness: &[f64],
+    children: &mut [NeuralNetwork],
+    layer: usize,
+    node: usize,
+    method: CrossoverMethod,
+    weight_index_buffer: &mut Vec<usize>,
+) {
+    match method {
+        CrossoverMethod::SwapWholeNode => {
+            let (a_weights, b_weights) = node_weights_pair(rng, children, layer, node);
+
+            for (w_a, w_b) in a_weights.iter_mut().zip(b_weights) {
+                mem::swap(w_a, w_b);
+            }
+        }
+        CrossoverMethod::SwapSomeWeights {
+            min_weights_swapped_ratio,
+            max_weights_swapped_ratio,
+        } => {
+            let (a_weights, b_weights) = node_weights_pair(rng, children, layer, node);
+
+            let node_weights = a_weights.len();
+            let weights_to_swap = (node_weights as f64
+                * rng.gen_range(min_weights_swapped_ratio, max_weights_swapped_ratio))
+            .trunc() as usize;
+
+            weight_index_buffer.resize_with(weights_to_swap, Default::default);
+
+            (0..node_weights).choose_multiple_fill(rng, &mut weight_index_buffer[..]);
+
+            for w_idx in weight_index_buffer.iter().copied() {
+                mem::swap(&mut a_weights[w_idx], &mut b_weights[w_idx]);
+            }
+        }
+        CrossoverMethod::UniformNode => {
+            for child in children.iter_mut() {
+                let parent = parents.choose(rng).unwrap();
+
+                child.layers_mut()[layer]
+                    .node_weights_mut(node)
+                    .unwrap()
+                    .copy_from_slice(parent.layers()[layer].node_weights(node).unwrap());
+            }
+        }
+        CrossoverMethod::FitnessWeightedAverage => {
+            // Fitness can't act as a weight unless it's positive, so fall back to equal weights
+            let total = input_fitness.iter().sum::<f64>();
+            let uniform = total <= 0.0 || input_fitness.iter().any(|&f| f <= 0.0);
+
+            let child = children.choose_mut(rng).unwrap();
+            let child_weights = child.layers_mut()[layer].node_weights_mut(node).unwrap();
+
+            for w in child_weights.iter_mut() {
+                *w = 0.0;
+            }
+
+            for (parent, &f) in parents.iter().zip(input_fitness) {
+                let share = if uniform {
+                    1.0 / parents.len() as f64
+                } else {
+                    f / total
+                };
+
+                for (w, p) in child_weights
+                    .iter_mut()
+                    .zip(parent.layers()[layer].node_weights(node).unwrap())
+                {
+                    *w += share * p;
                 }
             }
-            CrossoverMethod::SwapSomeWeights {
-                min_weights_swapped_ratio,
-                max_weights_swapped_ratio,
-            } => {
-                let node_weights = a_weights.len();
-                let weights_to_swap = (node_weights as f64
-                    * rng.gen_range(min_weights_swapped_ratio, max_weights_swapped_ratio))
-                .trunc() as usize;
+        }
+        CrossoverMethod::Arithmetic {
+            min_alpha,
+            max_alpha,
+        } => {
+            let (a_weights, b_weights) = node_weights_pair(rng, children, layer, node);
+            let alpha = gen_uniform(rng, min_alpha, max_alpha);
 
-                weight_index_buffer.resize_with(weights_to_swap, Default::default);
+            for (w_a, w_b) in a_weights.iter_mut().zip(b_weights) {
+                let (a, b) = (*w_a, *w_b);
 
-                (0..node_weights).choose_multiple_fill(rng, &mut weight_index_buffer[..]);
+                *w_a = alpha * a + (1.0 - alpha) * b;
+                *w_b = alpha * b + (1.0 - alpha) * a;
+            }
+        }
+        CrossoverMethod::BlendAlpha { alpha } => {
+            let (a_weights, b_weights) = node_weights_pair(rng, children, layer, node);
 
-                for w_idx in weight_index_buffer.iter().copied() {
-                    mem::swap(&mut a_weights[w_idx], &mut b_weights[w_idx]);
+            for (w_a, w_b) in a_weights.iter_mut().zip(b_weights) {
+                let (low, high) = (w_a.min(*w_b), w_a.max(*w_b));
+                let extension = alpha * (high - low);
+
+                *w_a = gen_uniform(rng, low - extension, high + extension);
+                *w_b = gen_uniform(rng, low - extension, high + extension);
+            }
+        }
+        CrossoverMethod::SimulatedBinary { eta, probability } => {
+            let (a_weights, b_weights) = node_weights_pair(rng, children, layer, node);
+
+            for (w_a, w_b) in a_weights.iter_mut().zip(b_weights) {
+                if rng.gen_range(0.0, 1.0) >= probability {
+                    continue;
                 }
+
+                let u: f64 = rng.gen_range(0.0, 1.0);
+                let beta = if u <= 0.5 {
+                    (2.0 * u).powf(1.0 / (eta + 1.0))
+                } else {
+                    (1.0 / (2.0 * (1.0 - u))).powf(1.0 / (eta + 1.0))
+                };
+
+                let (a, b) = (*w_a, *w_b);
+
+                *w_a = 0.5 * ((1.0 + beta) * a + (1.0 - beta) * b);
+                *w_b = 0.5 * ((1.0 - beta) * a + (1.0 + beta) * b);
             }
         }
+        // Never drawn per node
+        CrossoverMethod::SwapLayer | CrossoverMethod::KPoint { .. } => {}
+    }
+}
+
+/// Uniform sample from [low, high), which is just `low` for an empty range
+fn gen_uniform<R: Rng + ?Sized>(rng: &mut R, low: f64, high: f64) -> f64 {
+    if low < high {
+        rng.gen_range(low, high)
+    } else {
+        low
     }
+}
 
-    output.write(a);
-    output.write(b);
+/// Two distinct, randomly chosen children
+fn children_pair<'a, R: Rng + ?Sized>(
+    rng: &mut R,
+    children: &'a mut [NeuralNetwork],
+) -> (&'a mut NeuralNetwork, &'a mut NeuralNetwork) {
+    let picked = rand::seq::index::sample(rng, children.len(), 2);
+    let (low, high) = (
+        picked.index(0).min(picked.index(1)),
+        picked.index(0).max(picked.index(1)),
+    );
+
+    let (head, tail) = children.split_at_mut(high);
+
+    (&mut head[low], &mut tail[0])
+}
+
+/// Weights of the same node in two distinct, randomly chosen children
+fn node_weights_pair<'a, R: Rng + ?Sized>(
+    rng: &mut R,
+    children: &'a mut [NeuralNetwork],
+    layer: usize,
+    node: usize,
+) -> (&'a mut [f64], &'a mut [f64]) {
+    let (a, b) = children_pair(rng, children);
+
+    (
+        a.layers_mut()[layer].node_weights_mut(node).unwrap(),
+        b.layers_mut()[layer].node_weights_mut(node).unwrap(),
+    )
 }
 
 pub fn mutate<R: Rng + ?Sized>(rng: &mut R, nn: &mut NeuralNetwork, settings: &MutationSettings) {
@@ -65,8 +277,13 @@ pub fn mutate<R: Rng + ?Sized>(rng: &mut R, nn: &mut NeuralNetwork, settings: &M
         return;
     }
 
+    if let Some(self_adaptation) = settings.self_adaptation() {
+        adapt_mutation_sigmas(rng, nn, self_adaptation);
+    }
+
     for _ in 0..settings.gen_weights_affected(rng) {
         let layer = rng.gen_range(0, nn.layers().len());
+        let sigma = nn.mutation_sigma(layer).unwrap_or(0.0);
         let weight = nn.layers_mut()[layer]
             .all_weights_mut()
             .choose_mut(rng)
@@ -83,6 +300,607 @@ pub fn mutate<R: Rng + ?Sized>(rng: &mut R, nn: &mut NeuralNetwork, settings: &M
             mutation_settings::MutationMethod::Shift(min, max) => {
                 *weight += rng.gen_range(min, max)
             }
+            mutation_settings::MutationMethod::SelfAdaptiveGaussian => {
+                *weight += sigma * gen::standard_normal(rng)
+            }
+        }
+    }
+}
+
+/// Gives networks without (matching) step sizes their initial ones, then mutates the step sizes
+/// log-normally
+pub fn adapt_mutation_sigmas<R: Rng + ?Sized>(
+    rng: &mut R,
+    nn: &mut NeuralNetwork,
+    settings: &SelfAdaptationTemplate,
+) {
+    let sigma_count = if settings.per_layer {
+        nn.layers().len()
+    } else {
+        1
+    };
+
+    if nn.mutation_sigmas().len() != sigma_count {
+        *nn.mutation_sigmas_mut() = vec![settings.initial_sigma; sigma_count];
+    }
+
+    for sigma in nn.mutation_sigmas_mut().iter_mut() {
+        *sigma = (*sigma * (settings.learning_rate * gen::standard_normal(rng)).exp())
+            .max(settings.min_sigma)
+            .min(settings.max_sigma);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::BrainsError, nn::Activation};
+    use rand::rngs::StdRng;
+
+    fn network(weight: f64) -> NeuralNetwork {
+        let mut nn = NeuralNetwork::new(
+            3,
+            vec![vec![Activation::TanH; 5], vec![Activation::Linear; 2]],
+        );
+        let weights = vec![weight; nn.total_weights()];
+        nn.set_flattened_weights(&weights);
+        nn
+    }
+
+    fn cross(
+        rng: &mut StdRng,
+        method: CrossoverMethod,
+    ) -> (NeuralNetwork, NeuralNetwork, Vec<NeuralNetwork>) {
+        let children = cross_parents(rng, method, &[1.0, 2.0], &[1.0, 1.0], 0.5);
+
+        (network(1.0), network(2.0), children)
+    }
+
+    /// Crosses over parents whose weights are all `weights[i]`
+    fn cross_parents(
+        rng: &mut StdRng,
+        method: CrossoverMethod,
+        weights: &[f64],
+        fitness: &[f64],
+        nodes_affected_ratio: f64,
+    ) -> Vec<NeuralNetwork> {
+        let parents = weights.iter().map(|&w| network(w)).collect::<Vec<_>>();
+
+        let settings = CrossoverSettings::new(
+            &CrossoverSettingsTemplate {
+                parents: parents.len(),
+                align_nodes: false,
+                min_nodes_affected_ratio: nodes_affected_ratio,
+                max_nodes_affected_ratio: nodes_affected_ratio,
+                methods: vec![CrossoverMethodProbability {
+                    method,
+                    relative_probability: 1.0,
+                }],
+            },
+            &parents[0],
+        )
+        .unwrap();
+
+        let mut children = Vec::new();
+        crossover(
+            rng,
+            &parents.iter().collect::<Vec<_>>(),
+            fitness,
+            &mut SpecimenWriter::new(parents.len(), &mut children),
+            &settings,
+            &mut Vec::new(),
+        );
+
+        children
+    }
+
+    /// Every distinct weight value in the children
+    fn child_weights(children: &[NeuralNetwork]) -> Vec<f64> {
+        let mut weights = children
+            .iter()
+            .flat_map(|c| c.flattened_weights())
+            .collect::<Vec<_>>();
+        weights.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+        weights.dedup();
+        weights
+    }
+
+    fn self_adaptation(per_layer: bool) -> SelfAdaptationTemplate {
+        SelfAdaptationTemplate {
+            initial_sigma: 0.2,
+            learning_rate: 1.0,
+            min_sigma: 0.1,
+            max_sigma: 0.5,
+            per_layer,
+        }
+    }
+
+    #[test]
+    fn mutation_sigmas_adapt_within_bounds() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut nn = network(0.0);
+
+        adapt_mutation_sigmas(&mut rng, &mut nn, &self_adaptation(true));
+        assert_eq!(nn.mutation_sigmas().len(), 2);
+
+        let mut seen = Vec::new();
+        for _ in 0..100 {
+            adapt_mutation_sigmas(&mut rng, &mut nn, &self_adaptation(true));
+
+            assert!(nn.mutation_sigmas().iter().all(|s| (0.1..=0.5).contains(s)));
+            seen.extend_from_slice(nn.mutation_sigmas());
+        }
+
+        // The bounds are reached, and there is room in between
+        assert!(seen.contains(&0.1) && seen.contains(&0.5));
+        assert!(seen.iter().any(|&s| s > 0.1 && s < 0.5));
+
+        // Step sizes of the wrong granularity start over
+        adapt_mutation_sigmas(&mut rng, &mut nn, &self_adaptation(false));
+        assert_eq!(nn.mutation_sigmas().len(), 1);
+    }
+
+    #[test]
+    fn self_adaptive_gaussian_follows_layer_sigma() {
+        let mut rng = StdRng::seed_from_u64(12);
+
+        let template = serde_json::from_str::<MutationSettingsTemplate>(
+            r#"{
+                "mutation_probability": 1.0,
+                "min_weights_affected_ratio": 1.0,
+                "max_weights_affected_ratio": 1.0,
+                "methods": [{ "method": "SelfAdaptiveGaussian", "relative_probability": 1.0 }],
+                "self_adaptation": {
+                    "initial_sigma": 1.0,
+                    "learning_rate": 0.0,
+                    "min_sigma": 1e-12,
+                    "max_sigma": 1.0,
+                    "per_layer": true
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let mut nn = network(0.0);
+        let settings = MutationSettings::new(&template, &nn).unwrap();
+
+        // Without a learning rate, the step sizes stay as they are
+        *nn.mutation_sigmas_mut() = vec![1e-12, 1.0];
+        mutate(&mut rng, &mut nn, &settings);
+
+        assert_eq!(nn.mutation_sigmas(), &[1e-12, 1.0]);
+        assert!(nn.layers()[0].all_weights().iter().all(|w| w.abs() < 1e-9));
+        assert!(nn.layers()[1].all_weights().iter().any(|w| w.abs() > 1e-3));
+    }
+
+    #[test]
+    fn aligned_nodes_match_and_keep_function() {
+        let mut rng = StdRng::seed_from_u64(2);
+
+        let mut a = network(0.0);
+        let weights = (0..a.total_weights())
+            .map(|_| rng.gen_range(-1.0, 1.0))
+            .collect::<Vec<_>>();
+        a.set_flattened_weights(&weights);
+
+        // b computes the same function as a, with its hidden nodes in reverse order
+        let mut b = a.clone();
+        let reverse = (0..5).rev().collect::<Vec<_>>();
+        b.layers_mut()[0].permute_nodes(&reverse);
+        b.layers_mut()[1].permute_inputs(&reverse);
+
+        let input = [0.3, -0.7, 0.1];
+        let expected = a.evaluate(&input).to_vec();
+        let same_output = |nn: &NeuralNetwork| {
+            nn.evaluate(&input)
+                .iter()
+                .zip(&expected)
+                .all(|(o, e)| (o - e).abs() < 1e-12)
+        };
+
+        assert_ne!(a.flattened_weights(), b.flattened_weights());
+        assert!(same_output(&b));
+
+        b.align_to(&a);
+
+        assert!(b.is_structurally_equal(&a));
+        assert_eq!(a.flattened_weights(), b.flattened_weights());
+        assert!(same_output(&b));
+    }
+
+    #[test]
+    fn swap_layer_keeps_structure_and_whole_layers() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for _ in 0..100 {
+            let (a, b, children) = cross(&mut rng, CrossoverMethod::SwapLayer);
+
+            assert_eq!(children.len(), 2);
+
+            for child in &children {
+                assert!(child.is_structurally_equal(&a));
+                assert!(child.is_structurally_equal(&b));
+
+                // Every layer comes from one parent as a whole
+                for layer in child.layers().iter() {
+                    let weights = layer.all_weights();
+                    assert!(weights.iter().all(|&w| w == weights[0]));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn k_point_keeps_structure_and_segments() {
+        let mut rng = StdRng::seed_from_u64(1);
+
+        for &points in &[1, 2, 5, 1000] {
+            for _ in 0..100 {
+                let (a, b, children) = cross(&mut rng, CrossoverMethod::KPoint { points });
+
+                assert_eq!(children.len(), 2);
+
+                let a_genome = children[0].flattened_weights();
+                let b_genome = children[1].flattened_weights();
+
+                for child in &children {
+                    assert!(child.is_structurally_equal(&a));
+                    assert!(child.is_structurally_equal(&b));
+                }
+
+                // The children are complementary at every position
+                assert!(a_genome.iter().zip(&b_genome).all(|(a, b)| a + b == 3.0));
+                assert!(a_genome.iter().all(|&w| w == 1.0 || w == 2.0));
+            }
+        }
+    }
+
+    #[test]
+    fn uniform_node_draws_from_every_parent() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut seen = Vec::new();
+
+        for _ in 0..20 {
+            let children = cross_parents(
+                &mut rng,
+                CrossoverMethod::UniformNode,
+                &[1.0, 2.0, 3.0],
+                &[1.0; 3],
+                0.5,
+            );
+
+            assert_eq!(children.len(), 3);
+
+            // Nodes are copied as a whole
+            for child in &children {
+                for layer in child.layers().iter() {
+                    for node in 0..layer.activations().len() {
+                        let weights = layer.node_weights(node).unwrap();
+                        assert!(weights.iter().all(|&w| w == weights[0]));
+                    }
+                }
+            }
+
+            seen.extend(child_weights(&children[..2]));
+        }
+
+        // The first two children also got nodes of the third parent
+        seen.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+        seen.dedup();
+        assert_eq!(seen, vec![1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn fitness_weighted_average_weights_parents_by_fitness() {
+        let mut rng = StdRng::seed_from_u64(6);
+        let parents = [1.0, 2.0, 6.0];
+
+        // (1 * 1 + 2 * 2 + 5 * 6) / 8, and the plain mean without positive fitness everywhere
+        for &(fitness, average) in &[([1.0, 2.0, 5.0], 4.375), ([1.0, 0.0, 2.0], 3.0)] {
+            let children = cross_parents(
+                &mut rng,
+                CrossoverMethod::FitnessWeightedAverage,
+                &parents,
+                &fitness,
+                0.5,
+            );
+
+            assert_eq!(children.len(), 3);
+
+            let weights = child_weights(&children);
+            assert!(weights.contains(&average));
+            assert!(weights.iter().all(|w| *w == average || parents.contains(w)));
+        }
+    }
+
+    fn settings_error(method: CrossoverMethod) -> Option<BrainsError> {
+        let template = CrossoverSettingsTemplate {
+            methods: vec![CrossoverMethodProbability {
+                method,
+                relative_probability: 1.0,
+            }],
+            ..Default::default()
+        };
+
+        CrossoverSettings::new(&template, &network(0.0)).err()
+    }
+
+    #[test]
+    fn arithmetic_children_stay_between_parents() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let method = CrossoverMethod::Arithmetic {
+            min_alpha: 0.2,
+            max_alpha: 0.8,
+        };
+
+        for _ in 0..100 {
+            let (_, _, children) = cross(&mut rng, method);
+
+            let a_genome = children[0].flattened_weights();
+            let b_genome = children[1].flattened_weights();
+
+            assert!(a_genome.iter().all(|w| (1.0..=2.0).contains(w)));
+            assert!(a_genome
+                .iter()
+                .zip(&b_genome)
+                .all(|(a, b)| (a + b - 3.0).abs() < 1e-12));
+        }
+    }
+
+    #[test]
+    fn blend_alpha_children_stay_in_extended_interval() {
+        let mut rng = StdRng::seed_from_u64(8);
+        let mut outside = false;
+
+        // One node per crossover, so every weight is blended at most once
+        for _ in 0..100 {
+            let children = cross_parents(
+                &mut rng,
+                CrossoverMethod::BlendAlpha { alpha: 0.5 },
+                &[1.0, 2.0],
+                &[1.0, 1.0],
+                0.15,
+            );
+
+            for child in &children {
+                let genome = child.flattened_weights();
+
+                assert!(genome.iter().all(|w| (0.5..=2.5).contains(w)));
+                outside |= genome.iter().any(|w| !(1.0..=2.0).contains(w));
+            }
+        }
+
+        assert!(outside);
+    }
+
+    #[test]
+    fn invalid_alphas_are_rejected() {
+        let arithmetic = |min_alpha, max_alpha| {
+            settings_error(CrossoverMethod::Arithmetic {
+                min_alpha,
+                max_alpha,
+            })
+        };
+
+        assert!(arithmetic(0.0, 1.0).is_none());
+        assert!(arithmetic(0.5, 0.5).is_none());
+
+        for &(min_alpha, max_alpha) in &[
+            (0.5, 0.4),
+            (-0.1, 0.5),
+            (0.5, 1.1),
+            (0.5, f64::NAN),
+            (f64::NAN, 0.5),
+            (0.0, f64::INFINITY),
+        ] {
+            assert!(matches!(
+                arithmetic(min_alpha, max_alpha),
+                Some(BrainsError::CrossoverInvalidArithmeticAlphas)
+            ));
+        }
+
+        for &alpha in &[-0.1, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                settings_error(CrossoverMethod::BlendAlpha { alpha }),
+                Some(BrainsError::CrossoverInvalidBlendAlpha)
+            ));
+        }
+    }
+
+    #[test]
+    fn simulated_binary_keeps_parent_mean() {
+        let mut rng = StdRng::seed_from_u64(9);
+        let mut changed = false;
+
+        for _ in 0..100 {
+            let (_, _, children) = cross(
+                &mut rng,
+                CrossoverMethod::SimulatedBinary {
+                    eta: 2.0,
+                    probability: 1.0,
+                },
+            );
+
+            let a_genome = children[0].flattened_weights();
+            let b_genome = children[1].flattened_weights();
+
+            assert!(a_genome
+                .iter()
+                .zip(&b_genome)
+                .all(|(a, b)| (a + b - 3.0).abs() < 1e-9));
+            changed |= a_genome.iter().any(|&w| w != 1.0 && w != 2.0);
+        }
+
+        assert!(changed);
+    }
+
+    #[test]
+    fn simulated_binary_without_probability_keeps_parents() {
+        let mut rng = StdRng::seed_from_u64(10);
+
+        for _ in 0..100 {
+            let (a, b, children) = cross(
+                &mut rng,
+                CrossoverMethod::SimulatedBinary {
+                    eta: 2.0,
+                    probability: 0.0,
+                },
+            );
+
+            assert_eq!(children[0].flattened_weights(), a.flattened_weights());
+            assert_eq!(children[1].flattened_weights(), b.flattened_weights());
+        }
+    }
+
+    #[test]
+    fn layer_and_genome_methods_apply_once() {
+        let mut rng = StdRng::seed_from_u64(3);
+
+        for _ in 0..100 {
+            let (_, _, children) = cross(&mut rng, CrossoverMethod::SwapLayer);
+
+            let swapped = children[0]
+                .layers()
+                .iter()
+                .filter(|l| l.all_weights()[0] == 2.0)
+                .count();
+            assert_eq!(swapped, 1);
+        }
+
+        for &points in &[1, 2, 5] {
+            for _ in 0..100 {
+                let (_, _, children) = cross(&mut rng, CrossoverMethod::KPoint { points });
+
+                let genome = children[0].flattened_weights();
+                let transitions = genome.windows(2).filter(|w| w[0] != w[1]).count();
+                assert_eq!(transitions, points);
+            }
+        }
+    }
+
+    #[test]
+    fn aligned_nodes_keep_their_activation() {
+        let mut a = NeuralNetwork::new(
+            2,
+            vec![
+                vec![Activation::TanH, Activation::Linear],
+                vec![Activation::Linear],
+            ],
+        );
+        a.set_flattened_weights(&[0.1, 0.2, 0.3, -0.4, 0.5, -0.6, 0.7, 0.8, 0.9]);
+
+        // The same weights as a, but each node with the other activation
+        let mut b = a.clone();
+        b.layers_mut()[0]
+            .activations_mut()
+            .copy_from_slice(&[Activation::Linear, Activation::TanH]);
+
+        b.align_to(&a);
+
+        let activations = b.layers()[0].activations().to_vec();
+        assert!(activations[0].is_same_kind(&Activation::TanH));
+        assert!(activations[1].is_same_kind(&Activation::Linear));
+        assert_eq!(b.layers()[0].node_weights(0), a.layers()[0].node_weights(1));
+    }
+
+    #[test]
+    fn custom_nodes_stay_in_place() {
+        fn first_input(input: &[f64]) -> f64 {
+            input[1]
+        }
+
+        let mut rng = StdRng::seed_from_u64(4);
+        let mut a = NeuralNetwork::new(
+            2,
+            vec![
+                vec![
+                    Activation::Custom(first_input),
+                    Activation::TanH,
+                    Activation::TanH,
+                ],
+                vec![Activation::Linear, Activation::TanH],
+                vec![Activation::Custom(first_input)],
+            ],
+        );
+        let weights = (0..a.total_weights())
+            .map(|_| rng.gen_range(-1.0, 1.0))
+            .collect::<Vec<_>>();
+        a.set_flattened_weights(&weights);
+
+        // Only the built-in hidden nodes of the first layer are swapped
+        let mut b = a.clone();
+        b.layers_mut()[0].permute_nodes(&[0, 2, 1]);
+        b.layers_mut()[1].permute_inputs(&[0, 2, 1]);
+        b.layers_mut()[1].permute_nodes(&[1, 0]);
+        b.layers_mut()[2].permute_inputs(&[1, 0]);
+
+        b.align_to(&a);
+
+        assert!(b.layers()[0].activations()[0].is_custom());
+        assert_eq!(b.layers()[0].all_weights(), a.layers()[0].all_weights());
+
+        // The second layer feeds a custom node, which might depend on the order of its inputs,
+        // so its nodes keep their order. Their biases don't move with the inputs.
+        assert!(b.layers()[1].activations()[0].is_same_kind(&Activation::TanH));
+        assert_eq!(
+            b.layers()[1].node_weights(0).unwrap()[0],
+            a.layers()[1].node_weights(1).unwrap()[0]
+        );
+    }
+
+    #[test]
+    fn node_methods_read_from_aligned_parents() {
+        let mut rng = StdRng::seed_from_u64(13);
+
+        let mut a = network(0.0);
+        let weights = (0..a.total_weights())
+            .map(|_| rng.gen_range(-1.0, 1.0))
+            .collect::<Vec<_>>();
+        a.set_flattened_weights(&weights);
+
+        // The same network as a, with its hidden nodes in reverse order
+        let mut b = a.clone();
+        let reverse = (0..5).rev().collect::<Vec<_>>();
+        b.layers_mut()[0].permute_nodes(&reverse);
+        b.layers_mut()[1].permute_inputs(&reverse);
+
+        for &method in &[
+            CrossoverMethod::UniformNode,
+            CrossoverMethod::FitnessWeightedAverage,
+        ] {
+            let settings = CrossoverSettings::new(
+                &CrossoverSettingsTemplate {
+                    parents: 2,
+                    align_nodes: true,
+                    min_nodes_affected_ratio: 1.0,
+                    max_nodes_affected_ratio: 1.0,
+                    methods: vec![CrossoverMethodProbability {
+                        method,
+                        relative_probability: 1.0,
+                    }],
+                },
+                &a,
+            )
+            .unwrap();
+
+            let mut children = Vec::new();
+            crossover(
+                &mut rng,
+                &[&a, &b],
+                &[1.0, 2.0],
+                &mut SpecimenWriter::new(2, &mut children),
+                &settings,
+                &mut Vec::new(),
+            );
+
+            // Once aligned, both parents are a, and so are all children
+            for child in &children {
+                assert!(child
+                    .flattened_weights()
+                    .iter()
+                    .zip(&weights)
+                    .all(|(c, w)| (c - w).abs() < 1e-12));
+            }
         }
     }
 }
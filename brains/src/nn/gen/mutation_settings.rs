@@ -9,6 +9,32 @@ pub struct MutationSettingsTemplate {
     pub min_weights_affected_ratio: f64,
     pub max_weights_affected_ratio: f64,
     pub methods: Vec<MutationMethodProbability>,
+    #[serde(default)]
+    pub self_adaptation: Option<SelfAdaptationTemplate>,
+}
+
+/// Evolution strategies style self-adaptation: Every network carries its own mutation step sizes,
+/// which are mutated log-normally before they are used by `MutationMethod::SelfAdaptiveGaussian`
+#[derive(Deserialize, Serialize, Clone)]
+pub struct SelfAdaptationTemplate {
+    pub initial_sigma: f64,
+    pub learning_rate: f64,
+    pub min_sigma: f64,
+    pub max_sigma: f64,
+    /// One step size per layer instead of one for the whole network
+    pub per_layer: bool,
+}
+
+impl Default for SelfAdaptationTemplate {
+    fn default() -> Self {
+        SelfAdaptationTemplate {
+            initial_sigma: 0.2,
+            learning_rate: 0.2,
+            min_sigma: 0.001,
+            max_sigma: 2.0,
+            per_layer: true,
+        }
+    }
 }
 
 impl Default for MutationSettingsTemplate {
@@ -39,16 +65,22 @@ impl Default for MutationSettingsTemplate {
                     relative_probability: 4.0,
                 },
             ],
+            self_adaptation: None,
         }
     }
 }
 
+#[derive(Clone)]
 pub struct MutationSettings {
     mutation_probability: f64,
+    min_weights_affected_ratio: f64,
+    max_weights_affected_ratio: f64,
+    total_weights: usize,
     min_weights_affected: usize,
     max_weights_affected: usize,
     methods: Vec<MutationMethod>,
     method_index: WeightedIndex<f64>,
+    self_adaptation: Option<SelfAdaptationTemplate>,
 }
 
 #[derive(Deserialize, Serialize, Clone)]
@@ -63,6 +95,8 @@ pub enum MutationMethod {
     Replace(f64, f64),
     Scale(f64, f64),
     Shift(f64, f64),
+    /// Adds normally distributed noise, scaled by the network's own step size for the layer
+    SelfAdaptiveGaussian,
 }
 
 impl MutationSettings {
@@ -76,12 +110,13 @@ impl MutationSettings {
             .layers()
             .iter()
             .map(|l| l.all_weights().len())
-            .sum::<usize>() as f64;
+            .sum::<usize>();
 
-        let min_weights_affected =
-            (template.min_weights_affected_ratio * total_weights).trunc() as usize;
-        let max_weights_affected =
-            (template.max_weights_affected_ratio * total_weights).trunc() as usize + 1;
+        let (min_weights_affected, max_weights_affected) = Self::weights_affected(
+            template.min_weights_affected_ratio,
+            template.max_weights_affected_ratio,
+            total_weights,
+        );
 
         let methods = template.methods.iter().map(|m| m.method).collect();
         let method_index = WeightedIndex::new(
@@ -95,17 +130,60 @@ impl MutationSettings {
 
         Ok(MutationSettings {
             mutation_probability: template.mutation_probability,
+            min_weights_affected_ratio: template.min_weights_affected_ratio,
+            max_weights_affected_ratio: template.max_weights_affected_ratio,
+            total_weights,
             min_weights_affected,
             max_weights_affected,
             methods,
             method_index,
+            self_adaptation: template.self_adaptation.clone(),
         })
     }
 
+    fn weights_affected(min_ratio: f64, max_ratio: f64, total_weights: usize) -> (usize, usize) {
+        let total_weights = total_weights as f64;
+
+        (
+            (min_ratio * total_weights).trunc() as usize,
+            (max_ratio * total_weights).trunc() as usize + 1,
+        )
+    }
+
+    /// Copy with the mutation probability and weights affected ratios multiplied by `scale`,
+    /// capped at 1
+    pub fn scaled(&self, scale: f64) -> MutationSettings {
+        let min_ratio = (self.min_weights_affected_ratio * scale).min(1.0);
+        let max_ratio = (self.max_weights_affected_ratio * scale).min(1.0);
+        let (min_weights_affected, max_weights_affected) =
+            Self::weights_affected(min_ratio, max_ratio, self.total_weights);
+
+        MutationSettings {
+            mutation_probability: (self.mutation_probability * scale).min(1.0),
+            min_weights_affected_ratio: min_ratio,
+            max_weights_affected_ratio: max_ratio,
+            min_weights_affected,
+            max_weights_affected,
+            ..self.clone()
+        }
+    }
+
     pub fn mutation_probability(&self) -> f64 {
         self.mutation_probability
     }
 
+    pub fn min_weights_affected_ratio(&self) -> f64 {
+        self.min_weights_affected_ratio
+    }
+
+    pub fn max_weights_affected_ratio(&self) -> f64 {
+        self.max_weights_affected_ratio
+    }
+
+    pub fn self_adaptation(&self) -> Option<&SelfAdaptationTemplate> {
+        self.self_adaptation.as_ref()
+    }
+
     pub fn gen_weights_affected<R: Rng + ?Sized>(&self, rng: &mut R) -> usize {
         rng.gen_range(self.min_weights_affected, self.max_weights_affected)
     }
@@ -151,6 +229,22 @@ impl MutationSettings {
                         return Err(BrainsError::MutationInvalidShiftMethodMinMax);
                     }
                 }
+                MutationMethod::SelfAdaptiveGaussian => {
+                    if template.self_adaptation.is_none() {
+                        return Err(BrainsError::MutationSelfAdaptiveMethodWithoutSettings);
+                    }
+                }
+            }
+        }
+
+        if let Some(sa) = &template.self_adaptation {
+            if sa.learning_rate < 0.0
+                || sa.min_sigma <= 0.0
+                || sa.max_sigma < sa.min_sigma
+                || sa.initial_sigma < sa.min_sigma
+                || sa.initial_sigma > sa.max_sigma
+            {
+                return Err(BrainsError::MutationInvalidSelfAdaptation);
             }
         }
 
@@ -4,7 +4,10 @@ use crate::error::BrainsError;
 use serde::{Deserialize, Serialize};
 use std::{
     cell::{Ref, RefCell},
+    cmp::Ordering,
+    collections::hash_map::DefaultHasher,
     fmt::Debug,
+    hash::{Hash, Hasher},
     iter,
     ops::Deref,
 };
@@ -51,6 +54,10 @@ impl Default for NeuralNetworkTemplate {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct NeuralNetwork {
     layers: RefCell<Vec<Layer>>,
+    /// Mutation step sizes for self-adaptive mutation. Either one per layer, one for the whole
+    /// network, or empty if self-adaptation isn't used.
+    #[serde(default)]
+    mutation_sigmas: Vec<f64>,
 }
 
 impl NeuralNetwork {
@@ -85,6 +92,7 @@ impl NeuralNetwork {
 
         Ok(NeuralNetwork {
             layers: RefCell::new(layers),
+            mutation_sigmas: Vec::new(),
         })
     }
 
@@ -106,6 +114,7 @@ impl NeuralNetwork {
 
         NeuralNetwork {
             layers: RefCell::new(layers),
+            mutation_sigmas: Vec::new(),
         }
     }
 
@@ -121,6 +130,70 @@ impl NeuralNetwork {
             })
     }
 
+    /// Mean absolute difference between corresponding weights. Networks with a different
+    /// structure are infinitely far apart.
+    pub fn compatibility_distance(&self, other: &NeuralNetwork) -> f64 {
+        if !self.is_structurally_equal(other) {
+            return f64::INFINITY;
+        }
+
+        let mut total = 0.0;
+        let mut count = 0;
+
+        for (s, o) in self.layers().iter().zip(other.layers().iter()) {
+            for (w_s, w_o) in s.all_weights().iter().zip(o.all_weights()) {
+                total += (w_s - w_o).abs();
+                count += 1;
+            }
+        }
+
+        total / count as f64
+    }
+
+    /// Hash of the structure and all weights, so that copies of the same network can be told
+    /// apart from other networks without comparing them weight by weight
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+
+        for layer in self.layers().iter() {
+            layer.weights.len().hash(&mut hasher);
+
+            for weight in layer.all_weights() {
+                weight.to_bits().hash(&mut hasher);
+            }
+        }
+
+        hasher.finish()
+    }
+
+    /// Reorders the hidden nodes of every layer to best match the nodes of `other` with the same
+    /// activation, by the similarity of their incoming weights. The incoming weights of the next
+    /// layer are permuted along with them, so the network still computes the same function.
+    /// Custom activations may depend on the order of their inputs, so custom nodes stay in place
+    /// and layers feeding into them aren't reordered at all. Does nothing for networks of a
+    /// different structure.
+    pub fn align_to(&mut self, other: &NeuralNetwork) {
+        if !self.is_structurally_equal(other) {
+            return;
+        }
+
+        let other_layers = other.layers.borrow();
+        let layers = self.layers.get_mut();
+
+        // The output nodes have a fixed meaning, so only hidden layers are reordered. Every layer
+        // is matched after its inputs have been aligned.
+        for idx in 1..layers.len() {
+            if layers[idx].activations.iter().any(Activation::is_custom) {
+                continue;
+            }
+
+            let permutation = layers[idx - 1].node_matching(&other_layers[idx - 1]);
+
+            layers[idx - 1].permute_nodes(&permutation);
+            layers[idx].permute_inputs(&permutation);
+        }
+    }
+
     pub fn total_nodes(&self) -> usize {
         self.layers
             .borrow()
@@ -129,6 +202,32 @@ impl NeuralNetwork {
             .sum()
     }
 
+    pub fn total_weights(&self) -> usize {
+        self.layers.borrow().iter().map(|l| l.weights.len()).sum()
+    }
+
+    /// All weights of all layers, as one flat genome
+    pub fn flattened_weights(&self) -> Vec<f64> {
+        self.layers
+            .borrow()
+            .iter()
+            .flat_map(|l| l.weights.iter().copied())
+            .collect()
+    }
+
+    /// Overwrites all weights from a flat genome, in the order of `flattened_weights`
+    pub fn set_flattened_weights(&mut self, weights: &[f64]) {
+        assert_eq!(weights.len(), self.total_weights());
+
+        let mut remaining = weights;
+
+        for layer in self.layers.get_mut().iter_mut() {
+            let (head, tail) = remaining.split_at(layer.weights.len());
+            layer.weights.copy_from_slice(head);
+            remaining = tail;
+        }
+    }
+
     pub fn layers<'a>(&'a self) -> impl Deref<Target = [Layer]> + 'a {
         Ref::map(self.layers.borrow(), |l| &l[..])
     }
@@ -137,6 +236,23 @@ impl NeuralNetwork {
         &mut self.layers.get_mut()[..]
     }
 
+    pub fn mutation_sigmas(&self) -> &[f64] {
+        &self.mutation_sigmas[..]
+    }
+
+    pub fn mutation_sigmas_mut(&mut self) -> &mut Vec<f64> {
+        &mut self.mutation_sigmas
+    }
+
+    /// Self-adaptive mutation step size for weights of the given layer
+    pub fn mutation_sigma(&self, layer: usize) -> Option<f64> {
+        match self.mutation_sigmas.len() {
+            0 => None,
+            1 => Some(self.mutation_sigmas[0]),
+            _ => self.mutation_sigmas.get(layer).copied(),
+        }
+    }
+
     pub fn input_count(&self) -> usize {
         self.layers.borrow()[0].input_count
     }
@@ -210,6 +326,86 @@ impl Layer {
             .nth(node)
     }
 
+    /// For every node of `other`, the index of the most similar node of this layer with the same
+    /// activation. Pairs are matched greedily from the smallest squared weight distance up. Custom
+    /// nodes are matched to themselves, and nodes without a counterpart of their activation fill
+    /// the remaining places in order.
+    fn node_matching(&self, other: &Layer) -> Vec<usize> {
+        let node_count = self.activations.len();
+        let mut pairs = Vec::with_capacity(node_count * node_count);
+
+        let mut matching = vec![None; node_count];
+        let mut matched = vec![false; node_count];
+
+        for own in 0..node_count {
+            if self.activations[own].is_custom() {
+                matching[own] = Some(own);
+                matched[own] = true;
+                continue;
+            }
+
+            let own_weights = self.node_weights(own).unwrap();
+
+            for target in 0..node_count {
+                if !self.activations[own].is_same_kind(&other.activations[target]) {
+                    continue;
+                }
+
+                let distance = own_weights
+                    .iter()
+                    .zip(other.node_weights(target).unwrap())
+                    .map(|(a, b)| (a - b) * (a - b))
+                    .sum::<f64>();
+
+                pairs.push((distance, own, target));
+            }
+        }
+
+        pairs.sort_unstable_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+        for (_, own, target) in pairs {
+            if matching[target].is_none() && !matched[own] {
+                matching[target] = Some(own);
+                matched[own] = true;
+            }
+        }
+
+        let mut unmatched = (0..node_count).filter(|&own| !matched[own]);
+
+        matching
+            .into_iter()
+            .map(|m| m.or_else(|| unmatched.next()).unwrap())
+            .collect()
+    }
+
+    /// Moves node `permutation[i]` to position `i`, together with its activation
+    fn permute_nodes(&mut self, permutation: &[usize]) {
+        let weights = permutation
+            .iter()
+            .flat_map(|&node| self.node_weights(node).unwrap().iter().copied())
+            .collect();
+        let activations = permutation
+            .iter()
+            .map(|&node| self.activations[node])
+            .collect();
+
+        self.weights = weights;
+        self.activations = activations;
+    }
+
+    /// Reorders the incoming weights of every node to match inputs reordered by `permute_nodes`
+    fn permute_inputs(&mut self, permutation: &[usize]) {
+        let mut buffer = Vec::with_capacity(self.input_count + 1);
+
+        for node_weights in self.weights.chunks_exact_mut(self.input_count + 1) {
+            buffer.clear();
+            buffer.push(node_weights[0]);
+            buffer.extend(permutation.iter().map(|&input| node_weights[input + 1]));
+
+            node_weights.copy_from_slice(&buffer);
+        }
+    }
+
     fn evaluate(&mut self, input: &[f64]) -> &[f64] {
         assert_eq!(input.len(), self.input_count);
 
@@ -245,6 +441,18 @@ impl Activation {
             Activation::Custom(f) => f(input),
         }
     }
+
+    pub fn is_custom(&self) -> bool {
+        matches!(self, Activation::Custom(_))
+    }
+
+    /// Whether both are the same built-in activation. Custom activations can't be compared.
+    pub fn is_same_kind(&self, other: &Activation) -> bool {
+        matches!(
+            (self, other),
+            (Activation::Linear, Activation::Linear) | (Activation::TanH, Activation::TanH)
+        )
+    }
 }
 
 impl Debug for Activation {
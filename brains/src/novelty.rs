@@ -0,0 +1,266 @@
+use crate::error::BrainsError;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct NoveltySettings {
+    /// Novelty is the mean distance to this many nearest neighbours
+    pub k: usize,
+    /// Number of the most novel members that are added to the archive every generation
+    pub archive_additions: usize,
+    /// Oldest entries are dropped once the archive grows beyond this size
+    pub max_archive_size: usize,
+    /// 0 selects on novelty alone, 1 on fitness alone, everything in between blends both
+    pub fitness_weight: f64,
+}
+
+impl Default for NoveltySettings {
+    fn default() -> Self {
+        NoveltySettings {
+            k: 15,
+            archive_additions: 2,
+            max_archive_size: 500,
+            fitness_weight: 0.0,
+        }
+    }
+}
+
+impl NoveltySettings {
+    pub fn validate(&self) -> Result<(), BrainsError> {
+        if self.k == 0 {
+            return Err(BrainsError::NoveltyNeighboursZero);
+        }
+
+        if self.fitness_weight < 0.0 || self.fitness_weight > 1.0 {
+            return Err(BrainsError::NoveltyInvalidFitnessWeight);
+        }
+
+        Ok(())
+    }
+}
+
+/// Behaviour descriptors of members from past generations that were novel at their time
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct NoveltyArchive {
+    descriptor_len: usize,
+    descriptors: Vec<Vec<f64>>,
+}
+
+impl NoveltyArchive {
+    pub fn len(&self) -> usize {
+        self.descriptors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.descriptors.is_empty()
+    }
+
+    /// Computes the selection score of every member from its fitness and its behaviour
+    /// descriptor, which is the `descriptor_len` sized chunk of `descriptors` at the member's
+    /// index. Also returns the most novel descriptors, which should be added to the archive once
+    /// the generation was evolved with these scores.
+    pub fn score(
+        &self,
+        fitness: &[f64],
+        descriptors: &[f64],
+        descriptor_len: usize,
+        settings: &NoveltySettings,
+    ) -> Result<(Vec<f64>, NoveltyAdditions), BrainsError> {
+        assert_eq!(fitness.len() * descriptor_len, descriptors.len());
+
+        if descriptor_len == 0 {
+            return Err(BrainsError::BehaviorDescriptorLengthZero);
+        }
+
+        if !self.descriptors.is_empty() && descriptor_len != self.descriptor_len {
+            return Err(BrainsError::BehaviorDescriptorLengthMismatch);
+        }
+
+        if fitness.iter().chain(descriptors).any(|v| !v.is_finite()) {
+            return Err(BrainsError::InvalidFitnessValue);
+        }
+
+        let novelty = descriptors
+            .chunks_exact(descriptor_len)
+            .enumerate()
+            .map(|(idx, descriptor)| {
+                let neighbours = descriptors
+                    .chunks_exact(descriptor_len)
+                    .enumerate()
+                    .filter(|(other, _)| *other != idx)
+                    .map(|(_, d)| d)
+                    .chain(self.descriptors.iter().map(|d| &d[..]));
+
+                sparseness(descriptor, neighbours, settings.k)
+            })
+            .collect::<Vec<_>>();
+
+        let mut most_novel = (0..novelty.len()).collect::<Vec<_>>();
+        most_novel.sort_unstable_by(|&a, &b| {
+            novelty[b]
+                .partial_cmp(&novelty[a])
+                .unwrap_or(Ordering::Equal)
+        });
+
+        let additions = NoveltyAdditions {
+            descriptor_len,
+            descriptors: most_novel
+                .into_iter()
+                .take(settings.archive_additions)
+                .map(|idx| descriptors[idx * descriptor_len..(idx + 1) * descriptor_len].to_vec())
+                .collect(),
+        };
+
+        let novelty = normalize(&novelty);
+        let fitness = normalize(fitness);
+
+        let scores = novelty
+            .iter()
+            .zip(&fitness)
+            .map(|(n, f)| (1.0 - settings.fitness_weight) * n + settings.fitness_weight * f)
+            .collect();
+
+        Ok((scores, additions))
+    }
+
+    /// Adds the most novel descriptors of a scored generation, dropping the oldest entries once
+    /// the archive grows beyond its maximum size
+    pub fn add(&mut self, additions: NoveltyAdditions, settings: &NoveltySettings) {
+        self.descriptor_len = additions.descriptor_len;
+        self.descriptors.extend(additions.descriptors);
+
+        if self.descriptors.len() > settings.max_archive_size {
+            let excess = self.descriptors.len() - settings.max_archive_size;
+            self.descriptors.drain(0..excess);
+        }
+    }
+}
+
+/// Descriptors chosen for the archive by `NoveltyArchive::score`
+pub struct NoveltyAdditions {
+    descriptor_len: usize,
+    descriptors: Vec<Vec<f64>>,
+}
+
+/// Mean euclidean distance to the `k` nearest neighbours
+fn sparseness<'a, I: Iterator<Item = &'a [f64]>>(
+    descriptor: &[f64],
+    neighbours: I,
+    k: usize,
+) -> f64 {
+    let mut distances = neighbours
+        .map(|n| {
+            descriptor
+                .iter()
+                .zip(n)
+                .map(|(a, b)| (a - b).powi(2))
+                .sum::<f64>()
+                .sqrt()
+        })
+        .collect::<Vec<_>>();
+
+    if distances.is_empty() {
+        return 0.0;
+    }
+
+    distances.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    distances.truncate(k);
+
+    distances.iter().sum::<f64>() / distances.len() as f64
+}
+
+/// Scales values into [0, 1]. All values end up at 1 if they are equal, so they can still be
+/// selected proportionately.
+fn normalize(values: &[f64]) -> Vec<f64> {
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+    if max > min {
+        values.iter().map(|v| (v - min) / (max - min)).collect()
+    } else {
+        vec![1.0; values.len()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(fitness_weight: f64) -> NoveltySettings {
+        NoveltySettings {
+            k: 2,
+            archive_additions: 1,
+            max_archive_size: 2,
+            fitness_weight,
+        }
+    }
+
+    #[test]
+    fn isolated_members_are_most_novel() {
+        let archive = NoveltyArchive::default();
+        let descriptors = [0.0, 0.0, 0.1, 0.0, 0.0, 0.1, 5.0, 5.0];
+
+        let (scores, additions) = archive
+            .score(&[0.0; 4], &descriptors, 2, &settings(0.0))
+            .unwrap();
+
+        assert_eq!(scores[3], 1.0);
+        assert!(scores[..3].iter().all(|&s| s < 0.1));
+        assert_eq!(additions.descriptors, vec![vec![5.0, 5.0]]);
+
+        // Scoring alone leaves the archive untouched
+        assert!(archive.is_empty());
+    }
+
+    #[test]
+    fn archive_keeps_newest_descriptors() {
+        let mut archive = NoveltyArchive::default();
+
+        for generation in 0..3 {
+            let value = generation as f64;
+            let (_, additions) = archive
+                .score(&[0.0, 0.0], &[value, 0.0], 1, &settings(0.0))
+                .unwrap();
+
+            archive.add(additions, &settings(0.0));
+        }
+
+        assert_eq!(archive.len(), 2);
+        assert_eq!(archive.descriptors, vec![vec![1.0], vec![2.0]]);
+
+        // Archived descriptors count as neighbours, so a member close to one is less novel
+        let (scores, _) = archive
+            .score(&[0.0, 0.0], &[2.1, -10.0], 1, &settings(0.0))
+            .unwrap();
+        assert!(scores[0] < scores[1]);
+
+        let result = archive.score(&[0.0], &[1.0, 1.0], 2, &settings(0.0));
+        assert!(matches!(
+            result,
+            Err(BrainsError::BehaviorDescriptorLengthMismatch)
+        ));
+    }
+
+    #[test]
+    fn equal_behaviour_and_fitness_stay_selectable() {
+        let archive = NoveltyArchive::default();
+
+        let (scores, _) = archive
+            .score(&[3.0; 3], &[1.0; 6], 2, &settings(0.5))
+            .unwrap();
+
+        assert_eq!(scores, vec![1.0; 3]);
+    }
+
+    #[test]
+    fn fitness_weight_blends_fitness_in() {
+        let archive = NoveltyArchive::default();
+        let descriptors = [0.0, 1.0, 3.0];
+
+        let (scores, _) = archive
+            .score(&[2.0, 0.0, 1.0], &descriptors, 1, &settings(1.0))
+            .unwrap();
+
+        assert_eq!(scores, vec![1.0, 0.0, 0.5]);
+    }
+}
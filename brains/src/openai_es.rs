@@ -0,0 +1,301 @@
+use crate::{
+    error::BrainsError,
+    gen::{self, FitnessShaping},
+    nn::NeuralNetwork,
+};
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct OpenAiEsSettings {
+    /// Standard deviation of the perturbations, in weight units
+    pub noise: f64,
+    pub learning_rate: f64,
+    /// Decay rates of Adam's first and second moment estimates
+    pub beta1: f64,
+    pub beta2: f64,
+    /// Pulls the weights towards zero, relative to the learning rate
+    pub weight_decay: f64,
+}
+
+impl Default for OpenAiEsSettings {
+    fn default() -> Self {
+        OpenAiEsSettings {
+            noise: 0.1,
+            learning_rate: 0.03,
+            beta1: 0.9,
+            beta2: 0.999,
+            weight_decay: 0.005,
+        }
+    }
+}
+
+impl OpenAiEsSettings {
+    pub fn validate(&self, population_size: usize) -> Result<(), BrainsError> {
+        if !self.noise.is_finite() || self.noise <= 0.0 {
+            return Err(BrainsError::OpenAiEsInvalidNoise);
+        }
+
+        if !self.learning_rate.is_finite()
+            || self.learning_rate <= 0.0
+            || !self.weight_decay.is_finite()
+            || self.weight_decay < 0.0
+        {
+            return Err(BrainsError::OpenAiEsInvalidLearningRate);
+        }
+
+        if !(0.0..1.0).contains(&self.beta1) || !(0.0..1.0).contains(&self.beta2) {
+            return Err(BrainsError::OpenAiEsInvalidAdamDecay);
+        }
+
+        // Every perturbation is evaluated together with its mirrored counterpart
+        if population_size == 0 || population_size % 2 != 0 {
+            return Err(BrainsError::OpenAiEsPopulationNotEven);
+        }
+
+        Ok(())
+    }
+}
+
+/// State of an OpenAI style natural evolution strategy. The population consists of mirrored
+/// Gaussian perturbations around one central weight vector, which follows the fitness gradient
+/// estimated from them using Adam.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct OpenAiEs {
+    center: Vec<f64>,
+    /// Adam's first and second moment estimates
+    first_moment: Vec<f64>,
+    second_moment: Vec<f64>,
+    steps: usize,
+}
+
+impl OpenAiEs {
+    pub fn new(center: Vec<f64>) -> OpenAiEs {
+        let n = center.len();
+
+        OpenAiEs {
+            center,
+            first_moment: vec![0.0; n],
+            second_moment: vec![0.0; n],
+            steps: 0,
+        }
+    }
+
+    /// Starts the search at the mean of all member weights, which is exactly the center for a
+    /// population of mirrored pairs
+    pub fn from_population(population: &[NeuralNetwork]) -> OpenAiEs {
+        let mut center = vec![0.0; population[0].total_weights()];
+
+        for nn in population {
+            for (c, w) in center.iter_mut().zip(nn.flattened_weights()) {
+                *c += w / population.len() as f64;
+            }
+        }
+
+        OpenAiEs::new(center)
+    }
+
+    pub fn dimension(&self) -> usize {
+        self.center.len()
+    }
+
+    pub fn center(&self) -> &[f64] {
+        &self.center[..]
+    }
+
+    /// The central network, which is what the strategy considers its best solution
+    pub fn champion(&self, template: &NeuralNetwork) -> NeuralNetwork {
+        let mut nn = template.clone();
+        nn.set_flattened_weights(&self.center);
+        nn
+    }
+
+    /// Draws `count` / 2 perturbations and returns the center plus and minus each of them
+    pub fn sample<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        count: usize,
+        settings: &OpenAiEsSettings,
+    ) -> Vec<Vec<f64>> {
+        let mut samples = Vec::with_capacity(count);
+
+        for _ in 0..count / 2 {
+            let perturbation = (0..self.dimension())
+                .map(|_| settings.noise * gen::standard_normal(rng))
+                .collect::<Vec<_>>();
+
+            samples.push(
+                self.center
+                    .iter()
+                    .zip(&perturbation)
+                    .map(|(c, p)| c + p)
+                    .collect(),
+            );
+            samples.push(
+                self.center
+                    .iter()
+                    .zip(&perturbation)
+                    .map(|(c, p)| c - p)
+                    .collect(),
+            );
+        }
+
+        samples
+    }
+
+    /// Estimates the fitness gradient from the evaluated `samples` and takes one Adam step
+    pub fn update(
+        &mut self,
+        samples: &[Vec<f64>],
+        fitness: &[f64],
+        settings: &OpenAiEsSettings,
+    ) -> Result<(), BrainsError> {
+        assert_eq!(samples.len(), fitness.len());
+
+        let shaped = gen::shape_fitness(fitness, FitnessShaping::CenteredRank)?;
+        let scale = 1.0 / (samples.len() as f64 * settings.noise * settings.noise);
+
+        let mut gradient = self
+            .center
+            .iter()
+            .map(|c| -settings.weight_decay * c)
+            .collect::<Vec<_>>();
+
+        // The perturbation of every sample is its offset from the center
+        for (sample, f) in samples.iter().zip(&shaped) {
+            for ((g, x), c) in gradient.iter_mut().zip(sample).zip(&self.center) {
+                *g += scale * f * (x - c);
+            }
+        }
+
+        self.steps += 1;
+
+        let first_correction = 1.0 - settings.beta1.powi(self.steps as i32);
+        let second_correction = 1.0 - settings.beta2.powi(self.steps as i32);
+
+        for (((c, m), v), g) in self
+            .center
+            .iter_mut()
+            .zip(&mut self.first_moment)
+            .zip(&mut self.second_moment)
+            .zip(&gradient)
+        {
+            *m = settings.beta1 * *m + (1.0 - settings.beta1) * g;
+            *v = settings.beta2 * *v + (1.0 - settings.beta2) * g * g;
+
+            *c += settings.learning_rate * (*m / first_correction)
+                / ((*v / second_correction).sqrt() + 1e-8);
+        }
+
+        Ok(())
+    }
+}
+
+/// Replaces the population by new mirrored perturbations, after moving the center along the
+/// fitness gradient estimated from the current members
+pub fn evolve_openai_es<R: Rng + ?Sized>(
+    rng: &mut R,
+    population: &[NeuralNetwork],
+    fitness: &[f64],
+    state: &mut OpenAiEs,
+    settings: &OpenAiEsSettings,
+    offspring_count: usize,
+) -> Result<Vec<NeuralNetwork>, BrainsError> {
+    let samples = population
+        .iter()
+        .map(|nn| nn.flattened_weights())
+        .collect::<Vec<_>>();
+
+    state.update(&samples, fitness, settings)?;
+
+    Ok(state
+        .sample(rng, offspring_count, settings)
+        .into_iter()
+        .map(|weights| {
+            let mut nn = population[0].clone();
+            nn.set_flattened_weights(&weights);
+            nn
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+
+    fn settings() -> OpenAiEsSettings {
+        OpenAiEsSettings {
+            weight_decay: 0.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn samples_are_mirrored_around_center() {
+        let mut rng = StdRng::seed_from_u64(6);
+        let state = OpenAiEs::new(vec![1.0, -2.0, 0.5]);
+
+        let samples = state.sample(&mut rng, 6, &settings());
+
+        assert_eq!(samples.len(), 6);
+
+        for pair in samples.chunks_exact(2) {
+            for ((a, b), c) in pair[0].iter().zip(&pair[1]).zip(state.center()) {
+                assert!((a + b - 2.0 * c).abs() < 1e-12);
+                assert_ne!(a, c);
+            }
+        }
+    }
+
+    #[test]
+    fn first_step_follows_gradient_by_learning_rate() {
+        let mut rng = StdRng::seed_from_u64(8);
+        let mut state = OpenAiEs::new(vec![0.0, 0.0]);
+
+        let samples = state.sample(&mut rng, 200, &settings());
+        let fitness = samples.iter().map(|x| x[0] - x[1]).collect::<Vec<_>>();
+        state.update(&samples, &fitness, &settings()).unwrap();
+
+        // Adam normalizes the first step to the learning rate in every direction
+        let learning_rate = settings().learning_rate;
+        assert!((state.center()[0] - learning_rate).abs() < 1e-6);
+        assert!((state.center()[1] + learning_rate).abs() < 1e-6);
+    }
+
+    #[test]
+    fn converges_on_sphere() {
+        let mut rng = StdRng::seed_from_u64(8);
+        let mut state = OpenAiEs::new(vec![1.0, -1.0, 0.5]);
+
+        for _ in 0..300 {
+            let samples = state.sample(&mut rng, 20, &settings());
+            let fitness = samples
+                .iter()
+                .map(|x| -x.iter().map(|v| v * v).sum::<f64>())
+                .collect::<Vec<_>>();
+
+            state.update(&samples, &fitness, &settings()).unwrap();
+        }
+
+        assert!(state.center().iter().all(|c| c.abs() < 0.1));
+    }
+
+    #[test]
+    fn population_must_be_mirrored_pairs() {
+        assert!(settings().validate(4).is_ok());
+        assert!(matches!(
+            settings().validate(5),
+            Err(BrainsError::OpenAiEsPopulationNotEven)
+        ));
+
+        let settings = OpenAiEsSettings {
+            beta2: 1.0,
+            ..settings()
+        };
+        assert!(matches!(
+            settings.validate(4),
+            Err(BrainsError::OpenAiEsInvalidAdamDecay)
+        ));
+    }
+}
@@ -0,0 +1,408 @@
+use crate::{
+    error::BrainsError,
+    gen::{self, Breeding, SelectionMethod, SpecimenWriter},
+};
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct SpeciationSettings {
+    /// The compatibility threshold is adjusted every generation to get close to this many species
+    pub target_species: usize,
+    pub initial_threshold: f64,
+    pub threshold_step: f64,
+    pub min_threshold: f64,
+}
+
+impl Default for SpeciationSettings {
+    fn default() -> Self {
+        SpeciationSettings {
+            target_species: 5,
+            initial_threshold: 1.0,
+            threshold_step: 0.05,
+            min_threshold: 0.01,
+        }
+    }
+}
+
+impl SpeciationSettings {
+    pub fn validate(&self) -> Result<(), BrainsError> {
+        if self.target_species == 0 {
+            return Err(BrainsError::SpeciationTargetSpeciesZero);
+        }
+
+        if self.min_threshold < 0.0
+            || self.initial_threshold < self.min_threshold
+            || self.threshold_step < 0.0
+        {
+            return Err(BrainsError::SpeciationInvalidThreshold);
+        }
+
+        Ok(())
+    }
+}
+
+/// Species assignment of the current members, plus everything needed to keep species stable
+/// across generations
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Species<S> {
+    threshold: f64,
+    next_id: usize,
+    representatives: Vec<(usize, S)>,
+    member_species: Vec<usize>,
+}
+
+impl<S: Clone> Species<S> {
+    pub fn new(settings: &SpeciationSettings) -> Species<S> {
+        Species {
+            threshold: settings.initial_threshold,
+            next_id: 0,
+            representatives: Vec::new(),
+            member_species: Vec::new(),
+        }
+    }
+
+    /// Species ID of every member, in member order
+    pub fn member_species(&self) -> &[usize] {
+        &self.member_species[..]
+    }
+
+    pub fn threshold(&self) -> f64 {
+        self.threshold
+    }
+
+    pub fn is_assigned_to(&self, population: &[S]) -> bool {
+        self.member_species.len() == population.len()
+    }
+
+    /// Puts every member into the first species whose representative is closer than the current
+    /// threshold, or into a new species otherwise. Afterwards, picks new representatives among the
+    /// members and moves the threshold towards the target species count.
+    pub fn assign<R, D>(
+        &mut self,
+        rng: &mut R,
+        population: &[S],
+        distance: D,
+        settings: &SpeciationSettings,
+    ) where
+        R: Rng + ?Sized,
+        D: Fn(&S, &S) -> f64,
+    {
+        self.member_species.clear();
+
+        for specimen in population {
+            let existing = self
+                .representatives
+                .iter()
+                .find(|(_, r)| distance(specimen, r) < self.threshold)
+                .map(|(id, _)| *id);
+
+            let id = match existing {
+                Some(id) => id,
+                None => {
+                    let id = self.next_id;
+                    self.next_id += 1;
+                    self.representatives.push((id, specimen.clone()));
+                    id
+                }
+            };
+
+            self.member_species.push(id);
+        }
+
+        let member_species = &self.member_species;
+
+        // Extinct species are dropped, surviving ones are represented by a random member
+        self.representatives
+            .retain(|(id, _)| member_species.contains(id));
+
+        for (id, representative) in &mut self.representatives {
+            let members = member_species
+                .iter()
+                .enumerate()
+                .filter(|(_, s)| *s == id)
+                .map(|(idx, _)| idx);
+
+            if let Some(idx) = members.choose(rng) {
+                *representative = population[idx].clone();
+            }
+        }
+
+        if self.representatives.len() < settings.target_species {
+            self.threshold = (self.threshold - settings.threshold_step).max(settings.min_threshold);
+        } else if self.representatives.len() > settings.target_species {
+            self.threshold += settings.threshold_step;
+        }
+    }
+}
+
+/// Like `gen::evolve`, but every species breeds on its own. The number of children of a species
+/// is proportional to its summed fitness after explicit fitness sharing, i.e. after dividing every
+/// member's fitness by the size of its species. Negative fitness is shifted up for this, so the
+/// worst member counts as zero. Assigns the new generation to species as well.
+pub fn evolve_speciated<R, S, C, CS, M, MS, D>(
+    rng: &mut R,
+    population: &[S],
+    fitness: &[f64],
+    species: &mut Species<S>,
+    settings: &SpeciationSettings,
+    distance: D,
+    breeding: &Breeding<C, CS, M, MS>,
+) -> Result<Vec<S>, BrainsError>
+where
+    S: Clone,
+    R: Rng + ?Sized,
+    C: Fn(&mut R, &[&S], &[f64], &mut SpecimenWriter<S>, &CS, &mut Vec<usize>),
+    M: Fn(&mut R, &mut S, &MS),
+    D: Fn(&S, &S) -> f64,
+{
+    assert_eq!(population.len(), fitness.len());
+
+    gen::validate_fitness(fitness)?;
+
+    if !species.is_assigned_to(population) {
+        species.assign(rng, population, &distance, settings);
+    }
+
+    let mut output = Vec::with_capacity(population.len());
+
+    gen::add_elitism_members(
+        &mut SpecimenWriter::new(population.len(), &mut output),
+        population,
+        fitness,
+        breeding.elitism,
+    );
+
+    let species_ids = {
+        let mut ids = species.member_species().to_vec();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    };
+
+    let species_sizes = species_ids
+        .iter()
+        .map(|id| species.member_species().iter().filter(|s| *s == id).count())
+        .collect::<Vec<_>>();
+
+    // Offspring shares must not be negative. Selection within a species still sees the raw
+    // values and rejects them if it can't handle them.
+    let offset = fitness.iter().copied().fold(0.0, f64::min);
+
+    // Explicit fitness sharing. Summing the shared fitness of a species gives its mean fitness.
+    let adjusted_fitness = species_ids
+        .iter()
+        .zip(&species_sizes)
+        .map(|(id, &size)| {
+            species
+                .member_species()
+                .iter()
+                .zip(fitness)
+                .filter(|(s, _)| *s == id)
+                .map(|(_, f)| (f - offset) / size as f64)
+                .sum::<f64>()
+        })
+        .collect::<Vec<_>>();
+
+    let offspring_counts = allocate_offspring(&adjusted_fitness, population.len() - output.len());
+
+    let mut species_members = Vec::new();
+    let mut species_fitness = Vec::new();
+
+    for (id, &count) in species_ids.iter().zip(&offspring_counts) {
+        if count == 0 {
+            continue;
+        }
+
+        species_members.clear();
+        species_fitness.clear();
+
+        for ((specimen, &f), s) in population.iter().zip(fitness).zip(species.member_species()) {
+            if s == id {
+                species_members.push(specimen.clone());
+                species_fitness.push(f);
+            }
+        }
+
+        // Species without any fitness still get children if every species is equally bad, so
+        // pick their parents uniformly
+        let method = if species_fitness.iter().all(|&f| f == 0.0) {
+            SelectionMethod::Uniform
+        } else {
+            breeding.selection_method
+        };
+
+        let limit = output.len() + count;
+
+        gen::breed(
+            rng,
+            &species_members,
+            &species_fitness,
+            &Breeding {
+                selection_method: method,
+                ..breeding.by_ref()
+            },
+            &mut SpecimenWriter::new(limit, &mut output),
+        )?;
+    }
+
+    // mutation
+    output
+        .iter_mut()
+        .skip(breeding.elitism)
+        .for_each(|s| breeding.mutate(rng, s));
+
+    species.assign(rng, &output, &distance, settings);
+
+    Ok(output)
+}
+
+/// Splits `total` children among species proportional to `weights`, using largest remainders
+fn allocate_offspring(weights: &[f64], total: usize) -> Vec<usize> {
+    let sum = weights.iter().sum::<f64>();
+
+    let shares = if sum > 0.0 {
+        weights
+            .iter()
+            .map(|w| w / sum * total as f64)
+            .collect::<Vec<_>>()
+    } else {
+        vec![total as f64 / weights.len() as f64; weights.len()]
+    };
+
+    let mut counts = shares
+        .iter()
+        .map(|s| s.floor() as usize)
+        .collect::<Vec<_>>();
+    let mut remaining = total - counts.iter().sum::<usize>();
+
+    let mut by_remainder = (0..shares.len()).collect::<Vec<_>>();
+    by_remainder.sort_unstable_by(|&a, &b| {
+        (shares[b] - shares[b].floor())
+            .partial_cmp(&(shares[a] - shares[a].floor()))
+            .unwrap()
+    });
+
+    for idx in by_remainder {
+        if remaining == 0 {
+            break;
+        }
+
+        counts[idx] += 1;
+        remaining -= 1;
+    }
+
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen::tests::marking_breeding;
+    use rand::rngs::StdRng;
+
+    fn settings() -> SpeciationSettings {
+        SpeciationSettings {
+            target_species: 2,
+            initial_threshold: 1.0,
+            threshold_step: 0.1,
+            min_threshold: 0.1,
+        }
+    }
+
+    fn distance(a: &f64, b: &f64) -> f64 {
+        (a - b).abs()
+    }
+
+    #[test]
+    fn close_members_share_a_species() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let population = [0.0, 10.0, 0.1, 10.2, 0.3];
+
+        let mut species = Species::new(&settings());
+        species.assign(&mut rng, &population, distance, &settings());
+
+        let ids = species.member_species();
+        assert_eq!(ids[0], ids[2]);
+        assert_eq!(ids[0], ids[4]);
+        assert_eq!(ids[1], ids[3]);
+        assert_ne!(ids[0], ids[1]);
+
+        // Already at the target species count
+        assert_eq!(species.threshold(), 1.0);
+    }
+
+    #[test]
+    fn offspring_are_allocated_by_largest_remainder() {
+        assert_eq!(allocate_offspring(&[1.0, 3.0, 6.0], 7), vec![1, 2, 4]);
+        assert_eq!(allocate_offspring(&[0.0, 2.0], 5), vec![0, 5]);
+
+        let counts = allocate_offspring(&[0.0, 0.0], 5);
+        assert_eq!(counts.iter().sum::<usize>(), 5);
+        assert!(counts.iter().all(|&c| c == 2 || c == 3));
+    }
+
+    #[test]
+    fn species_get_offspring_by_shared_fitness() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let population = [0.0, 0.1, 0.2, 10.0, 10.1];
+        // Both species sum to 3, but the smaller one has the higher mean
+        let fitness = [1.0, 1.0, 1.0, 1.5, 1.5];
+
+        let mut species = Species::new(&settings());
+        let breeding = marking_breeding(SelectionMethod::FitnessProportionate, 0);
+
+        let next_gen = evolve_speciated(
+            &mut rng,
+            &population,
+            &fitness,
+            &mut species,
+            &settings(),
+            distance,
+            &breeding,
+        )
+        .unwrap();
+
+        // Shares are 5 * 1 / 2.5 = 2 and 5 * 1.5 / 2.5 = 3
+        assert_eq!(next_gen.iter().filter(|&&s| s < 15.0).count(), 2);
+        assert_eq!(next_gen.iter().filter(|&&s| s >= 15.0).count(), 3);
+        assert!(species.is_assigned_to(&next_gen));
+    }
+
+    #[test]
+    fn negative_fitness_depends_on_selection_method() {
+        let population = [0.0, 0.1, 10.0, 10.1];
+        let fitness = [-1.0, -2.0, -3.0, -4.0];
+
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut species = Species::new(&settings());
+
+        let next_gen = evolve_speciated(
+            &mut rng,
+            &population,
+            &fitness,
+            &mut species,
+            &settings(),
+            distance,
+            &marking_breeding(SelectionMethod::Tournament(2), 1),
+        )
+        .unwrap();
+
+        assert_eq!(next_gen.len(), 4);
+        assert_eq!(next_gen[0], 0.0);
+
+        let mut species = Species::new(&settings());
+
+        let result = evolve_speciated(
+            &mut rng,
+            &population,
+            &fitness,
+            &mut species,
+            &settings(),
+            distance,
+            &marking_breeding(SelectionMethod::FitnessProportionate, 1),
+        );
+
+        assert!(matches!(result, Err(BrainsError::NegativeFitness)));
+    }
+}
@@ -6,6 +6,11 @@ using UnityEngine;
 
 public static unsafe class BrainsDll
 {
+    // Error codes returned when the fitness values don't suit the configured selection, see
+    // brains/src/error.rs
+    public const int AllFitnessZero = 703;
+    public const int NegativeFitness = 704;
+
     [DllImport("brains", CallingConvention = CallingConvention.Cdecl)]
     [return: MarshalAs(UnmanagedType.LPStr)]
     public static extern string get_last_error();
@@ -22,12 +27,51 @@ public static unsafe class BrainsDll
     [DllImport("brains", CallingConvention = CallingConvention.Cdecl)]
     public static extern ushort evolve_population(void* population, double* fitness);
 
+    [DllImport("brains", CallingConvention = CallingConvention.Cdecl)]
+    public static extern ushort evolve_population_with_behavior(void* population, double* fitness, double* descriptors, ulong descriptorLen);
+
+    [DllImport("brains", CallingConvention = CallingConvention.Cdecl)]
+    public static extern ushort evolve_population_multi_objective(void* population, double* objectives, ulong objectiveCount);
+
+    [DllImport("brains", CallingConvention = CallingConvention.Cdecl)]
+    public static extern ushort map_elites_insert(void* population, double* fitness, double* descriptors, ulong* inserted);
+
+    [DllImport("brains", CallingConvention = CallingConvention.Cdecl)]
+    public static extern ushort map_elites_sample(void* population, ulong count);
+
+    [DllImport("brains", CallingConvention = CallingConvention.Cdecl)]
+    public static extern ushort evolve_steady_state(void* population, double* fitness, ulong k, ulong* replacedIndices);
+
+    [DllImport("brains", CallingConvention = CallingConvention.Cdecl)]
+    public static extern ushort get_member_count(void* population, ulong* count);
+
+    [DllImport("brains", CallingConvention = CallingConvention.Cdecl)]
+    public static extern ushort get_species_ids(void* population, ulong* speciesIds);
+
+    [DllImport("brains", CallingConvention = CallingConvention.Cdecl)]
+    public static extern ushort get_trial_replacements(void* population, bool* replaced);
+
+    [DllImport("brains", CallingConvention = CallingConvention.Cdecl)]
+    public static extern ushort get_effective_mutation(void* population, double* mutationProbability, double* minWeightsAffectedRatio, double* maxWeightsAffectedRatio, double* scale);
+
+    [DllImport("brains", CallingConvention = CallingConvention.Cdecl)]
+    public static extern ushort get_mutation_sigmas(void* population, ulong index, double* sigmas, ulong capacity, ulong* count);
+
     [DllImport("brains", CallingConvention = CallingConvention.Cdecl)]
     public static extern ushort drop_population(void* population);
 
     [DllImport("brains", CallingConvention = CallingConvention.Cdecl)]
     public static extern ushort save_top_n([MarshalAs(UnmanagedType.LPStr)] string path, void* population, double* fitness, ulong n);
 
+    [DllImport("brains", CallingConvention = CallingConvention.Cdecl)]
+    public static extern ushort save_pareto_front([MarshalAs(UnmanagedType.LPStr)] string path, void* population, double* objectives, ulong objectiveCount);
+
+    [DllImport("brains", CallingConvention = CallingConvention.Cdecl)]
+    public static extern ushort save_map_elites_archive([MarshalAs(UnmanagedType.LPStr)] string path, void* population);
+
+    [DllImport("brains", CallingConvention = CallingConvention.Cdecl)]
+    public static extern ushort save_hall_of_fame([MarshalAs(UnmanagedType.LPStr)] string path, void* population);
+
     [DllImport("brains", CallingConvention = CallingConvention.Cdecl)]
     public static extern ushort save_all([MarshalAs(UnmanagedType.LPStr)] string path, void* population);
 
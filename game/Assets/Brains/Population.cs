@@ -6,12 +6,30 @@ using UnityEngine;
 
 public class Population : IDisposable
 {
+    /// <summary>
+    /// Mutation that is currently applied when evolving, after the adaptive mutation controller
+    /// scaled the configured values
+    /// </summary>
+    public struct EffectiveMutation
+    {
+        public double MutationProbability;
+        public double MinWeightsAffectedRatio;
+        public double MaxWeightsAffectedRatio;
+        public double Scale;
+
+        /// <summary>
+        /// Mutation was raised because evolution stagnated
+        /// </summary>
+        public bool IsExploring => Scale > 1.0;
+    }
+
     private unsafe void* _population;
 
     /// <summary>
-    /// Number of neural networks in the entire population
+    /// Number of neural networks in the entire population. Can change after evolving, depending on
+    /// the generation scheme in the config.
     /// </summary>
-    public readonly ulong Size;
+    public ulong Size { get; private set; }
 
     /// <summary>
     /// Number of inputs for a single population member
@@ -113,8 +131,201 @@ public class Population : IDisposable
                 var ff = f;
                 ThrowOnError(() => BrainsDll.evolve_population(_population, ff));
             }
-            
         }
+
+        RefreshSize();
+    }
+
+    /// <summary>
+    /// Evolves based on novelty (or a blend of novelty and fitness, depending on the config)
+    /// </summary>
+    /// <param name="descriptors">One behaviour descriptor of length descriptorLen per member, back to back</param>
+    public void EvolveWithBehavior(double[] fitness, double[] descriptors, ulong descriptorLen)
+    {
+        unsafe
+        {
+            fixed (double* f = fitness)
+            fixed (double* d = descriptors)
+            {
+                var ff = f;
+                var dd = d;
+                ThrowOnError(() => BrainsDll.evolve_population_with_behavior(_population, ff, dd, descriptorLen));
+            }
+        }
+
+        RefreshSize();
+    }
+
+    /// <summary>
+    /// Evolves with NSGA-II. All objectives are maximized. Parents stay in the population together
+    /// with their children, so the member count doubles after the first generation.
+    /// </summary>
+    /// <param name="objectives">objectiveCount values per member, back to back</param>
+    public void EvolveMultiObjective(double[] objectives, ulong objectiveCount)
+    {
+        unsafe
+        {
+            fixed (double* o = objectives)
+            {
+                var oo = o;
+                ThrowOnError(() => BrainsDll.evolve_population_multi_objective(_population, oo, objectiveCount));
+            }
+        }
+
+        RefreshSize();
+    }
+
+    /// <summary>
+    /// Inserts all evaluated members into the MAP-Elites archive
+    /// </summary>
+    /// <param name="descriptors">One value per behaviour dimension and member, back to back</param>
+    /// <returns>Number of cells that got a new elite</returns>
+    public ulong InsertIntoMapElites(double[] fitness, double[] descriptors)
+    {
+        ulong inserted;
+
+        unsafe
+        {
+            ulong* inserted_ptr = &inserted;
+
+            fixed (double* f = fitness)
+            fixed (double* d = descriptors)
+            {
+                var ff = f;
+                var dd = d;
+                ThrowOnError(() => BrainsDll.map_elites_insert(_population, ff, dd, inserted_ptr));
+            }
+        }
+
+        return inserted;
+    }
+
+    /// <summary>
+    /// Replaces all members with count new candidates bred from the MAP-Elites archive
+    /// </summary>
+    public void SampleFromMapElites(ulong count)
+    {
+        unsafe
+        {
+            ThrowOnError(() => BrainsDll.map_elites_sample(_population, count));
+        }
+
+        RefreshSize();
+    }
+
+    /// <summary>
+    /// Replaces only the k weakest members with offspring. The generation only counts up once as
+    /// many members have been replaced as the population holds. Not available with speciation,
+    /// islands or ALPS.
+    /// </summary>
+    /// <returns>Indices of the replaced members</returns>
+    public ulong[] EvolveSteadyState(double[] fitness, ulong k)
+    {
+        var replaced = new ulong[k];
+
+        unsafe
+        {
+            fixed (double* f = fitness)
+            fixed (ulong* r = replaced)
+            {
+                var ff = f;
+                var rr = r;
+                ThrowOnError(() => BrainsDll.evolve_steady_state(_population, ff, k, rr));
+            }
+        }
+
+        return replaced;
+    }
+
+    /// <summary>
+    /// Species of every member. Only available if speciation is enabled in the config.
+    /// </summary>
+    public ulong[] GetSpeciesIds()
+    {
+        var speciesIds = new ulong[Size];
+
+        unsafe
+        {
+            fixed (ulong* s = speciesIds)
+            {
+                var ss = s;
+                ThrowOnError(() => BrainsDll.get_species_ids(_population, ss));
+            }
+        }
+
+        return speciesIds;
+    }
+
+    /// <summary>
+    /// For differential evolution, whether the member evaluated at each index replaced its target
+    /// in the last evolution. The current members are the next trials and haven't competed yet.
+    /// </summary>
+    public bool[] GetTrialReplacements()
+    {
+        var replaced = new bool[Size];
+
+        unsafe
+        {
+            fixed (bool* r = replaced)
+            {
+                var rr = r;
+                ThrowOnError(() => BrainsDll.get_trial_replacements(_population, rr));
+            }
+        }
+
+        return replaced;
+    }
+
+    public EffectiveMutation GetEffectiveMutation()
+    {
+        var mutation = new EffectiveMutation();
+
+        unsafe
+        {
+            double* probability_ptr = &mutation.MutationProbability;
+            double* min_ratio_ptr = &mutation.MinWeightsAffectedRatio;
+            double* max_ratio_ptr = &mutation.MaxWeightsAffectedRatio;
+            double* scale_ptr = &mutation.Scale;
+
+            ThrowOnError(() => BrainsDll.get_effective_mutation(_population, probability_ptr, min_ratio_ptr, max_ratio_ptr, scale_ptr));
+        }
+
+        return mutation;
+    }
+
+    /// <summary>
+    /// Self-adaptive mutation step sizes of a member. Empty if self-adaptation is disabled.
+    /// </summary>
+    public double[] GetMutationSigmas(ulong index)
+    {
+        var sigmas = new double[1];
+        ulong count;
+
+        unsafe
+        {
+            ulong* count_ptr = &count;
+
+            // The first call only reports the count if the buffer is too small
+            for (var attempt = 0; attempt < 2; attempt++)
+            {
+                fixed (double* s = sigmas)
+                {
+                    var ss = s;
+                    var capacity = (ulong)sigmas.Length;
+                    ThrowOnError(() => BrainsDll.get_mutation_sigmas(_population, index, ss, capacity, count_ptr));
+                }
+
+                if (count <= (ulong)sigmas.Length)
+                {
+                    break;
+                }
+
+                sigmas = new double[count];
+            }
+        }
+
+        Array.Resize(ref sigmas, (int)count);
+        return sigmas;
     }
 
     public void SaveTopN(string path, double[] fitness, ulong n)
@@ -129,6 +340,34 @@ public class Population : IDisposable
         }
     }
 
+    public void SaveParetoFront(string path, double[] objectives, ulong objectiveCount)
+    {
+        unsafe
+        {
+            fixed (double* o = objectives)
+            {
+                var oo = o;
+                ThrowOnError(() => BrainsDll.save_pareto_front(path, _population, oo, objectiveCount));
+            }
+        }
+    }
+
+    public void SaveMapElitesArchive(string path)
+    {
+        unsafe
+        {
+            ThrowOnError(() => BrainsDll.save_map_elites_archive(path, _population));
+        }
+    }
+
+    public void SaveHallOfFame(string path)
+    {
+        unsafe
+        {
+            ThrowOnError(() => BrainsDll.save_hall_of_fame(path, _population));
+        }
+    }
+
     public void SaveAll(string path)
     {
         unsafe
@@ -137,11 +376,26 @@ public class Population : IDisposable
         }
     }
 
+    private void RefreshSize()
+    {
+        unsafe
+        {
+            ulong size;
+            ulong* size_ptr = &size;
+
+            ThrowOnError(() => BrainsDll.get_member_count(_population, size_ptr));
+
+            Size = size;
+        }
+    }
+
     private static void ThrowOnError(Func<ushort> f)
     {
-        if (f() > 0)
+        var error = f();
+
+        if (error > 0)
         {
-            throw new ExternalException($"Native call to brains.dll failed with: {BrainsDll.get_last_error()}");
+            throw new ExternalException($"Native call to brains.dll failed with: {BrainsDll.get_last_error()}", error);
         }
     }
 
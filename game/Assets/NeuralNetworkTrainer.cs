@@ -4,6 +4,7 @@ using System.Collections;
 using System.Collections.Generic;
 using System.IO;
 using System.Linq;
+using System.Runtime.InteropServices;
 using UnityEngine;
 using UnityEngine.Assertions;
 using UnityEngine.Events;
@@ -55,6 +56,11 @@ public class NeuralNetworkTrainer : MonoBehaviour
 
     public int Generation { get; private set; }
 
+    /// <summary>
+    /// Mutation that will be used for the next evolution, null if the population isn't evolved
+    /// </summary>
+    public Population.EffectiveMutation? Mutation { get; private set; }
+
     public int TrackIndex { get; private set; }
 
     public int TrackCount => TrackSeeds == null ? 1 : TrackSeeds.Length;
@@ -107,6 +113,7 @@ public class NeuralNetworkTrainer : MonoBehaviour
             }
 
             Generation = (int)Population.LoadedGeneration;
+            Mutation = _evolveAfterRound ? Population.GetEffectiveMutation() : (Population.EffectiveMutation?)null;
 
             OnPopulationCreated.Invoke();
         }
@@ -275,17 +282,35 @@ public class NeuralNetworkTrainer : MonoBehaviour
 
             if (_evolveAfterRound)
             {
-                if (fitness.All(f => f == 0.0))
-                {
-                    Debug.LogWarning("Fitness was zero for the enitre population. Skipping evolution");
-                }
-                else
+                try
                 {
                     Population.Evolve(fitness);
                     Generation++;
+                    Mutation = Population.GetEffectiveMutation();
+                }
+                catch (ExternalException e) when (e.ErrorCode == BrainsDll.AllFitnessZero ||
+                                                  e.ErrorCode == BrainsDll.NegativeFitness)
+                {
+                    // Fitness values that the configured selection can't handle, everything else
+                    // is a real error
+                    Debug.LogWarning($"Skipping evolution: {e.Message}");
                 }
             }
 
+            if ((ulong)_neuralCars.Count != Population.Size)
+            {
+                // The generation scheme changed the number of members that need to be evaluated
+                _neuralCars.ForEach(car => Destroy(car.gameObject));
+                _neuralCars = SpawnCars();
+
+                fitness = new double[Population.Size];
+                speedBonusFitness = new double[Population.Size];
+                tracksFinished = new int[Population.Size];
+
+                // Wait one frame so all cars are properly spawned in and initialized
+                yield return null;
+            }
+
             // Reset fitness
             for (int i = 0; i < fitness.Length; i++)
             {
@@ -12,5 +12,10 @@ public class GenerationLabel : MonoBehaviour
     private void Update()
     {
         _label.text = $"Gen {_trainer.Generation} ({_trainer.TrackIndex + 1}/{_trainer.TrackCount})";
+
+        if (_trainer.Mutation is Population.EffectiveMutation mutation && mutation.IsExploring)
+        {
+            _label.text += $" exploring (mutation x{mutation.Scale:0.0})";
+        }
     }
 }